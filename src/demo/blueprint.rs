@@ -0,0 +1,331 @@
+//! Blueprints of the furniture a level asks for, and how close the player's build is to one.
+//!
+//! A build is a group of planks held together by joints. The group that comes closest to the
+//! level's blueprint is shown with the blueprint's outline, along with how complete it is.
+
+use crate::demo::joint::PlankJoint;
+use crate::demo::level::{CurrentLevel, LevelDef, Plank};
+use crate::demo::shape::PlankShape;
+use crate::screens::Screen;
+use crate::theme::prelude::*;
+use avian2d::position::{Position, Rotation};
+use bevy::asset::io::Reader;
+use bevy::asset::{AssetLoader, AsyncReadExt, LoadContext};
+use bevy::prelude::*;
+use bevy::ui::Val::Px;
+use bevy::utils::HashMap;
+use serde::{Deserialize, Serialize};
+
+pub(super) fn plugin(app: &mut App) {
+    app.init_asset::<Blueprint>();
+    app.register_asset_loader(BlueprintLoader);
+    app.init_resource::<BlueprintProgress>();
+    app.add_systems(OnEnter(Screen::Gameplay), spawn_progress_label);
+    app.add_systems(
+        Update,
+        (check_blueprint.run_if(build_changed), draw_blueprint, update_progress_label)
+            .chain()
+            .run_if(in_state(Screen::Gameplay)),
+    );
+}
+
+/// A piece of furniture to build.
+#[derive(Asset, TypePath, Serialize, Deserialize, Clone, Debug)]
+pub struct Blueprint {
+    pub name: String,
+    /// The planks the piece is made of.
+    pub parts: Vec<BlueprintPart>,
+    /// How many joints hold the piece together, at least.
+    pub joints: usize,
+    /// Size of the finished piece's bounding box.
+    pub size: Vec2,
+    /// How far a plank's dimensions may be off and still count as a part.
+    #[serde(default = "default_tolerance")]
+    pub tolerance: f32,
+}
+
+fn default_tolerance() -> f32 {
+    3.
+}
+
+impl Blueprint {
+    /// How far a plank of the given size is off from the closest part, in its worst dimension.
+    pub fn part_error(&self, size: Vec2) -> Option<f32> {
+        let size = sorted(size);
+        self.parts
+            .iter()
+            .map(|p| (sorted(p.size) - size).abs().max_element())
+            .min_by(f32::total_cmp)
+    }
+}
+
+#[derive(Serialize, Deserialize, Clone, Debug)]
+pub struct BlueprintPart {
+    /// Length and thickness of the plank. Which way round it is used doesn't matter.
+    pub size: Vec2,
+    #[serde(default = "default_count")]
+    pub count: usize,
+}
+
+fn default_count() -> usize {
+    1
+}
+
+#[derive(Default)]
+pub struct BlueprintLoader;
+
+#[derive(Debug, thiserror::Error)]
+pub enum BlueprintLoaderError {
+    #[error("could not read blueprint file: {0}")]
+    Io(#[from] std::io::Error),
+    #[error("could not parse blueprint file: {0}")]
+    Ron(#[from] ron::error::SpannedError),
+}
+
+impl AssetLoader for BlueprintLoader {
+    type Asset = Blueprint;
+    type Settings = ();
+    type Error = BlueprintLoaderError;
+
+    async fn load<'a>(
+        &'a self,
+        reader: &'a mut Reader<'_>,
+        _settings: &'a (),
+        _load_context: &'a mut LoadContext<'_>,
+    ) -> Result<Blueprint, BlueprintLoaderError> {
+        let mut bytes = Vec::new();
+        reader.read_to_end(&mut bytes).await?;
+        Ok(ron::de::from_bytes(&bytes)?)
+    }
+
+    fn extensions(&self) -> &[&str] {
+        &["blueprint.ron"]
+    }
+}
+
+/// How close the best build is to the current level's blueprint.
+#[derive(Resource, Default, Debug, PartialEq)]
+pub struct BlueprintProgress {
+    /// Planks of the build closest to the blueprint.
+    pub build: Vec<Entity>,
+    /// Bottom centre of the build's bounding box, where the blueprint is drawn.
+    pub anchor: Vec2,
+    /// Fraction of the required parts found in the build, between 0 and 1.
+    pub parts: f32,
+    /// Fraction of the required joints found in the build, between 0 and 1.
+    pub joints: f32,
+    /// How well the build's bounding box matches the blueprint's, between 0 and 1.
+    pub size: f32,
+}
+
+impl BlueprintProgress {
+    /// Overall completion, between 0 and 1.
+    pub fn completion(&self) -> f32 {
+        (self.parts + self.joints + self.size) / 3.
+    }
+}
+
+/// The blueprint of the current level, if it has one.
+pub fn current_blueprint<'a>(
+    current: &CurrentLevel,
+    levels: &'a Assets<LevelDef>,
+    blueprints: &'a Assets<Blueprint>,
+) -> Option<&'a Blueprint> {
+    blueprints.get(&levels.get(&current.0)?.blueprint_handle)
+}
+
+/// Groups planks that are joined to each other, directly or through other planks.
+pub fn connected_groups(
+    planks: impl IntoIterator<Item = Entity>,
+    joints: impl IntoIterator<Item = [Entity; 2]>,
+) -> Vec<Vec<Entity>> {
+    let mut group_of: HashMap<Entity, Entity> = planks.into_iter().map(|p| (p, p)).collect();
+    fn root(group_of: &mut HashMap<Entity, Entity>, mut e: Entity) -> Entity {
+        while group_of[&e] != e {
+            let parent = group_of[&group_of[&e]];
+            group_of.insert(e, parent);
+            e = parent;
+        }
+        e
+    }
+    for [a, b] in joints {
+        if !group_of.contains_key(&a) || !group_of.contains_key(&b) {
+            continue;
+        }
+        let (root_a, root_b) = (root(&mut group_of, a), root(&mut group_of, b));
+        group_of.insert(root_a, root_b);
+    }
+    let mut groups: HashMap<Entity, Vec<Entity>> = HashMap::new();
+    let planks: Vec<Entity> = group_of.keys().copied().collect();
+    for plank in planks {
+        let r = root(&mut group_of, plank);
+        groups.entry(r).or_default().push(plank);
+    }
+    groups.into_values().collect()
+}
+
+/// The build the player is working on: the one closest to the blueprint, or the biggest one if
+/// there is no blueprint.
+pub fn main_build(
+    progress: &BlueprintProgress,
+    planks: impl IntoIterator<Item = Entity>,
+    joints: impl IntoIterator<Item = [Entity; 2]>,
+) -> Vec<Entity> {
+    if !progress.build.is_empty() {
+        return progress.build.clone();
+    }
+    connected_groups(planks, joints)
+        .into_iter()
+        .max_by_key(Vec::len)
+        .unwrap_or_default()
+}
+
+/// Whether any plank or joint was added, moved, reshaped or removed since the last check.
+fn build_changed(
+    planks: Query<(), (With<Plank>, Or<(Changed<Position>, Changed<Rotation>, Changed<PlankShape>)>)>,
+    joints: Query<(), Added<PlankJoint>>,
+    mut removed_planks: RemovedComponents<Plank>,
+    mut removed_joints: RemovedComponents<PlankJoint>,
+) -> bool {
+    // Both removal readers are drained, so old removals don't count next time.
+    let removed = removed_planks.read().count() + removed_joints.read().count();
+    removed > 0 || !planks.is_empty() || !joints.is_empty()
+}
+
+fn check_blueprint(
+    current: Res<CurrentLevel>,
+    levels: Res<Assets<LevelDef>>,
+    blueprints: Res<Assets<Blueprint>>,
+    planks: Query<(Entity, &PlankShape, &Position, &Rotation), With<Plank>>,
+    joints: Query<&PlankJoint>,
+    mut progress: ResMut<BlueprintProgress>,
+) {
+    let Some(blueprint) = current_blueprint(&current, &levels, &blueprints) else {
+        progress.set_if_neq(default());
+        return;
+    };
+    let groups = connected_groups(planks.iter().map(|(e, ..)| e), joints.iter().map(|j| j.planks));
+    let best = groups
+        .into_iter()
+        .map(|group| score_build(blueprint, group, &planks, &joints))
+        .max_by(|a, b| a.completion().total_cmp(&b.completion()));
+    progress.set_if_neq(best.unwrap_or_default());
+}
+
+fn score_build(
+    blueprint: &Blueprint,
+    build: Vec<Entity>,
+    planks: &Query<(Entity, &PlankShape, &Position, &Rotation), With<Plank>>,
+    joints: &Query<&PlankJoint>,
+) -> BlueprintProgress {
+    let mut min = Vec2::splat(f32::INFINITY);
+    let mut max = Vec2::splat(f32::NEG_INFINITY);
+    let mut sizes = Vec::new();
+    for (_, shape, position, rotation) in planks.iter_many(&build) {
+        for v in shape.parts().iter().flatten() {
+            let world = position.0 + *rotation * *v;
+            min = min.min(world);
+            max = max.max(world);
+        }
+        sizes.push(sorted(shape.size()));
+    }
+
+    // Match every required part to its own plank.
+    let required: Vec<Vec2> = blueprint
+        .parts
+        .iter()
+        .flat_map(|p| std::iter::repeat(sorted(p.size)).take(p.count))
+        .collect();
+    let mut matched = 0;
+    for part in &required {
+        if let Some(i) = sizes
+            .iter()
+            .position(|s| (*s - *part).abs().max_element() <= blueprint.tolerance)
+        {
+            sizes.swap_remove(i);
+            matched += 1;
+        }
+    }
+
+    let joint_count = joints
+        .iter()
+        .filter(|j| build.contains(&j.planks[0]) && build.contains(&j.planks[1]))
+        .count();
+    let build_size = max - min;
+    let size_match = |a: f32, b: f32| if a.max(b) > 0. { a.min(b) / a.max(b) } else { 0. };
+
+    BlueprintProgress {
+        anchor: Vec2::new((min.x + max.x) / 2., min.y),
+        parts: if required.is_empty() { 1. } else { matched as f32 / required.len() as f32 },
+        joints: if blueprint.joints == 0 {
+            1.
+        } else {
+            (joint_count as f32 / blueprint.joints as f32).min(1.)
+        },
+        size: size_match(build_size.x, blueprint.size.x) * size_match(build_size.y, blueprint.size.y),
+        build,
+    }
+}
+
+/// The longer side first, so sizes compare the same whichever way a plank is turned.
+fn sorted(size: Vec2) -> Vec2 {
+    Vec2::new(size.x.max(size.y), size.x.min(size.y))
+}
+
+const BLUEPRINT_COLOR: Color = Color::srgba(0.3, 0.6, 1., 0.8);
+
+/// Draws the blueprint's outline over the closest build.
+fn draw_blueprint(
+    current: Res<CurrentLevel>,
+    levels: Res<Assets<LevelDef>>,
+    blueprints: Res<Assets<Blueprint>>,
+    progress: Res<BlueprintProgress>,
+    mut gizmos: Gizmos,
+) {
+    let Some(blueprint) = current_blueprint(&current, &levels, &blueprints) else {
+        return;
+    };
+    if progress.build.is_empty() {
+        return;
+    }
+    let center = progress.anchor + Vec2::Y * blueprint.size.y / 2.;
+    gizmos.rect_2d(center, 0., blueprint.size, BLUEPRINT_COLOR);
+}
+
+/// Text showing the blueprint's name and the build's completion.
+#[derive(Component)]
+struct ProgressLabel;
+
+fn spawn_progress_label(mut commands: Commands) {
+    commands.label("").insert((
+        Name::new("Blueprint progress"),
+        ProgressLabel,
+        StateScoped(Screen::Gameplay),
+        Style {
+            position_type: PositionType::Absolute,
+            right: Px(10.0),
+            top: Px(80.0),
+            ..default()
+        },
+    ));
+}
+
+fn update_progress_label(
+    current: Res<CurrentLevel>,
+    levels: Res<Assets<LevelDef>>,
+    blueprints: Res<Assets<Blueprint>>,
+    progress: Res<BlueprintProgress>,
+    mut labels: Query<&mut Text, With<ProgressLabel>>,
+    new_labels: Query<(), Added<ProgressLabel>>,
+) {
+    if !progress.is_changed() && new_labels.is_empty() {
+        return;
+    }
+    let text = match current_blueprint(&current, &levels, &blueprints) {
+        Some(blueprint) => format!("{}: {:.0}%", blueprint.name, progress.completion() * 100.),
+        None => String::new(),
+    };
+    for mut label in &mut labels {
+        label.sections[0].value.clone_from(&text);
+    }
+}
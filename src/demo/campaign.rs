@@ -0,0 +1,155 @@
+//! The campaign: a sequence of customer orders, each unlocked by fulfilling the one before.
+//!
+//! The orders are listed in `assets/campaign.ron`, and each one is a level with an
+//! [`OrderDef`](crate::demo::level::OrderDef). Fulfilled orders are remembered between sessions,
+//! and pay out to the [`Stock`] the first time.
+
+use crate::asset_tracking::LoadResource;
+use crate::demo::level::LevelDef;
+use crate::demo::save::{read_save, write_save, SaveError};
+use crate::demo::score::BuildResult;
+use crate::demo::shop::Stock;
+use crate::screens::Screen;
+use bevy::asset::io::Reader;
+use bevy::asset::{AssetLoader, AsyncReadExt, LoadContext};
+use bevy::prelude::*;
+use serde::{Deserialize, Serialize};
+use std::collections::BTreeMap;
+
+pub(super) fn plugin(app: &mut App) {
+    app.init_asset::<Campaign>();
+    app.register_asset_loader(CampaignLoader);
+    app.load_resource::<CampaignAssets>();
+    app.insert_resource(CampaignProgress::load().unwrap_or_else(|e| {
+        error!("{e}");
+        default()
+    }));
+    app.add_systems(OnEnter(Screen::Results), record_order);
+}
+
+/// The orders of the campaign, in the order they unlock.
+#[derive(Asset, TypePath, Serialize, Deserialize, Clone, Debug)]
+pub struct Campaign {
+    /// Paths of the orders' levels, relative to the assets folder.
+    pub orders: Vec<String>,
+    #[serde(skip)]
+    pub levels: Vec<Handle<LevelDef>>,
+}
+
+#[derive(Default)]
+pub struct CampaignLoader;
+
+#[derive(Debug, thiserror::Error)]
+pub enum CampaignLoaderError {
+    #[error("could not read campaign file: {0}")]
+    Io(#[from] std::io::Error),
+    #[error("could not parse campaign file: {0}")]
+    Ron(#[from] ron::error::SpannedError),
+}
+
+impl AssetLoader for CampaignLoader {
+    type Asset = Campaign;
+    type Settings = ();
+    type Error = CampaignLoaderError;
+
+    async fn load<'a>(
+        &'a self,
+        reader: &'a mut Reader<'_>,
+        _settings: &'a (),
+        load_context: &'a mut LoadContext<'_>,
+    ) -> Result<Campaign, CampaignLoaderError> {
+        let mut bytes = Vec::new();
+        reader.read_to_end(&mut bytes).await?;
+        let mut campaign: Campaign = ron::de::from_bytes(&bytes)?;
+        campaign.levels = campaign
+            .orders
+            .iter()
+            .map(|path| load_context.load(path.clone()))
+            .collect();
+        Ok(campaign)
+    }
+
+    fn extensions(&self) -> &[&str] {
+        &["campaign.ron"]
+    }
+}
+
+#[derive(Resource, Asset, Reflect, Clone)]
+pub struct CampaignAssets {
+    #[dependency]
+    pub campaign: Handle<Campaign>,
+}
+
+impl CampaignAssets {
+    pub const PATH_CAMPAIGN: &'static str = "campaign.ron";
+}
+
+impl FromWorld for CampaignAssets {
+    fn from_world(world: &mut World) -> Self {
+        let assets = world.resource::<AssetServer>();
+        Self {
+            campaign: assets.load(Self::PATH_CAMPAIGN),
+        }
+    }
+}
+
+/// Which orders have been fulfilled, kept between sessions.
+#[derive(Resource, Serialize, Deserialize, Default, Debug)]
+pub struct CampaignProgress {
+    /// Best star rating of every fulfilled order, by the path of its level.
+    pub fulfilled: BTreeMap<String, usize>,
+}
+
+impl CampaignProgress {
+    const NAME: &'static str = "campaign";
+
+    pub fn store(&self) -> Result<(), SaveError> {
+        write_save(Self::NAME, &ron::ser::to_string_pretty(self, ron::ser::PrettyConfig::default())?)
+    }
+
+    pub fn load() -> Result<Self, SaveError> {
+        match read_save(Self::NAME)? {
+            Some(contents) => Ok(ron::de::from_str(&contents)?),
+            None => Ok(default()),
+        }
+    }
+
+    /// Whether the order at `index` can be taken: the first one always can, the others once the
+    /// order before them has been fulfilled.
+    pub fn is_unlocked(&self, campaign: &Campaign, index: usize) -> bool {
+        index == 0
+            || campaign
+                .orders
+                .get(index - 1)
+                .is_some_and(|previous| self.fulfilled.contains_key(previous))
+    }
+}
+
+/// The campaign order being worked on. Absent when playing a level outside the campaign.
+#[derive(Resource, Clone, Debug)]
+pub struct ActiveOrder {
+    /// Path of the order's level, relative to the assets folder.
+    pub path: String,
+}
+
+fn record_order(
+    result: Res<BuildResult>,
+    order: Option<Res<ActiveOrder>>,
+    mut progress: ResMut<CampaignProgress>,
+    mut stock: ResMut<Stock>,
+) {
+    let (Some(order), Some(true)) = (order, result.fulfilled) else {
+        return;
+    };
+    if !progress.fulfilled.contains_key(&order.path) {
+        stock.money += result.order.as_ref().map_or(0, |o| o.payment);
+        if let Err(e) = stock.store() {
+            error!("{e}");
+        }
+    }
+    let best = progress.fulfilled.entry(order.path.clone()).or_default();
+    *best = (*best).max(result.stars());
+    if let Err(e) = progress.store() {
+        error!("{e}");
+    }
+}
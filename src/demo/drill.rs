@@ -0,0 +1,113 @@
+//! The drill, which bores round holes through planks for dowels or decoration.
+
+use crate::demo::history::{BuildAction, ParkPlank};
+use crate::demo::level::Plank;
+use crate::demo::shape::PlankShape;
+use crate::demo::wood::{Grain, PlankSpecies, WoodSpecies};
+use crate::demo::{get_world_pos, BuildPhase, HeldTool};
+use crate::screens::Screen;
+use avian2d::position::{Position, Rotation};
+use avian2d::prelude::MassPropertiesBundle;
+use bevy::prelude::*;
+use bevy::sprite::{MaterialMesh2dBundle, Mesh2dHandle};
+
+pub(super) fn plugin(app: &mut App) {
+    app.add_systems(
+        Update,
+        (toggle_drill, drill_holes).chain().run_if(in_state(BuildPhase::Building)),
+    );
+}
+
+/// The drill bit following the cursor while the drill is in hand.
+#[derive(Component)]
+pub struct Drill;
+
+/// Radius of drilled holes. Wide enough for a dowel to fit through.
+pub const DRILL_RADIUS: f32 = 2.;
+const DRILL_COLOR: Color = Color::srgba(0.3, 0.3, 0.35, 0.6);
+
+fn toggle_drill(
+    input: Res<ButtonInput<KeyCode>>,
+    drills: Query<Entity, With<Drill>>,
+    tools: Query<Entity, With<HeldTool>>,
+    mut meshes: ResMut<Assets<Mesh>>,
+    mut materials: ResMut<Assets<ColorMaterial>>,
+    mut commands: Commands,
+) {
+    if !input.just_pressed(KeyCode::KeyD) {
+        return;
+    }
+    if drills.is_empty() {
+        for tool in &tools {
+            commands.entity(tool).despawn();
+        }
+        commands.spawn((
+            Name::new("Drill"),
+            Drill,
+            HeldTool,
+            MaterialMesh2dBundle {
+                mesh: Mesh2dHandle(meshes.add(Circle::new(DRILL_RADIUS))),
+                material: materials.add(DRILL_COLOR),
+                transform: Transform::from_xyz(0., 200., 2.),
+                ..default()
+            },
+            StateScoped(Screen::Gameplay),
+        ));
+    } else {
+        for drill in &drills {
+            commands.entity(drill).despawn();
+        }
+    }
+}
+
+/// Clicking bores through every plank under the drill, so stacked planks line up for a dowel.
+fn drill_holes(
+    mut drill: Query<&mut Transform, With<Drill>>,
+    planks: Query<(Entity, &PlankShape, &Position, &Rotation, &Grain, &PlankSpecies), With<Plank>>,
+    species: Res<Assets<WoodSpecies>>,
+    window: Query<&Window>,
+    camera: Query<(&Camera, &GlobalTransform)>,
+    mouse: Res<ButtonInput<MouseButton>>,
+    mut meshes: ResMut<Assets<Mesh>>,
+    mut commands: Commands,
+) {
+    let Ok(mut drill) = drill.get_single_mut() else {
+        return;
+    };
+    let window = window.get_single().unwrap();
+    let (camera, gt) = camera.get_single().unwrap();
+    let Some(cursor) = get_world_pos(window, camera, gt).map(|p| p.truncate()) else {
+        return;
+    };
+    drill.translation = cursor.extend(drill.translation.z);
+    if !mouse.just_pressed(MouseButton::Left) {
+        return;
+    }
+    let drilled: Vec<_> = planks
+        .iter()
+        .filter_map(|(e, shape, position, rotation, grain, plank_species)| {
+            let local = rotation.inverse() * (cursor - position.0);
+            shape.contains(local).then_some((e, shape, local, grain, plank_species))
+        })
+        .collect();
+    if drilled.is_empty() {
+        return;
+    }
+    // The planks change through commands, so they are still whole when the action is recorded.
+    commands.trigger(BuildAction::new("Drill", drilled.iter().map(|(e, ..)| *e)));
+    for (e, shape, local, grain, plank_species) in drilled {
+        let mut shape = shape.clone();
+        shape.drill(local, DRILL_RADIUS);
+        match shape.collider() {
+            Some(collider) => {
+                // The wood bored out no longer counts towards the plank's mass.
+                let density = species.get(&plank_species.0).map_or(WoodSpecies::default().density, |s| s.density);
+                let mass = MassPropertiesBundle::new_computed(&collider, density);
+                let mesh = Mesh2dHandle(meshes.add(shape.mesh(grain.direction())));
+                commands.entity(e).insert((shape, collider, mass, mesh));
+            }
+            // Nothing is left of the plank.
+            None => commands.add(ParkPlank(e)),
+        }
+    }
+}
@@ -0,0 +1,533 @@
+//! The level editor. Planks, props and the saw can be placed, resized, rotated and coloured,
+//! then saved as a level file or tried out straight away.
+//!
+//! Everything in the editor is frozen in place, so the layout is exactly what gets saved.
+//! A level's random pile is saved as the individual planks it was dealt as.
+
+use crate::demo::level::{
+    spawn_prop, spawn_saw, ColliderDef, CurrentLevel, LevelDef, Plank, PlankDef, Prop, PropDef,
+    PropPart, Saw, SawBody, SawDef, ShapeDef,
+};
+use crate::demo::shape::PlankShape;
+use crate::demo::wood::{Grain, PlankSpecies, WoodAssets, WoodSpecies};
+use crate::demo::{get_world_pos, pick_at, ColorInfo, Selectable, SpawnPlank};
+use crate::screens::Screen;
+use crate::theme::prelude::*;
+use avian2d::collision::Collider;
+use avian2d::position::{Position, Rotation};
+use avian2d::prelude::RigidBody;
+use bevy::ecs::system::SystemParam;
+use bevy::prelude::*;
+use bevy::sprite::Mesh2dHandle;
+use bevy::ui::Val::Px;
+
+pub(super) fn plugin(app: &mut App) {
+    app.init_resource::<EditorDrag>();
+    app.add_systems(OnEnter(Screen::Editor), spawn_editor_ui);
+    #[cfg(not(target_family = "wasm"))]
+    {
+        app.init_resource::<EditedLevelPath>();
+        app.add_systems(OnEnter(Screen::Editor), remember_level_path);
+    }
+    app.add_systems(
+        Update,
+        (freeze_bodies, pick_and_drag, edit_selected, follow_saw_body, highlight_selected)
+            .chain()
+            .run_if(in_state(Screen::Editor)),
+    );
+}
+
+/// Marks what is selected in the editor. Separate from gameplay's
+/// [`Selected`](crate::demo::Selected), so gameplay systems leave it alone.
+#[derive(Component)]
+struct EditorSelected;
+
+/// Where the selected object was grabbed, in its local frame, while it is being dragged.
+#[derive(Resource, Default)]
+struct EditorDrag(Option<Vec2>);
+
+const ROTATE_STEP: f32 = 5.;
+const LENGTH_STEP: f32 = 5.;
+const THICKNESS_STEP: f32 = 1.;
+const MIN_PLANK_SIZE: f32 = 2.;
+const SCALE_STEP: f32 = 1.1;
+const NEW_PLANK_POSITION: Vec2 = Vec2::new(0., 150.);
+const NEW_PLANK_SIZE: Vec2 = Vec2::new(100., 10.);
+/// How far duplicates are placed from the original.
+const DUPLICATE_OFFSET: Vec2 = Vec2::new(10., 15.);
+/// Colours cycled through with `C`.
+const PLANK_COLORS: [Color; 5] = [
+    Color::srgb(0.3, 0.15, 0.01),
+    Color::srgb(0.45, 0.27, 0.1),
+    Color::srgb(0.6, 0.42, 0.2),
+    Color::srgb(0.75, 0.6, 0.38),
+    Color::srgb(0.2, 0.1, 0.05),
+];
+/// sRGB colours cycled through with `C` on props drawn by their parts.
+const PROP_COLORS: [[f32; 3]; 5] = [
+    [0.5, 0.5, 0.5],
+    [0.7, 0.2, 0.15],
+    [0.2, 0.4, 0.7],
+    [0.25, 0.55, 0.25],
+    [0.85, 0.7, 0.2],
+];
+const NEW_PROP_SIZE: f32 = 20.;
+const NEW_PROP_MASS: f32 = 10.;
+const HIGHLIGHT_COLOR: Color = Color::srgb(1., 0.85, 0.1);
+const HELP_TEXT: &str =
+    "Drag to move, Q/E rotate, arrow keys resize, C colour, W wood, G grain, Delete removes, Esc quits";
+
+fn spawn_editor_ui(mut commands: Commands) {
+    commands
+        .toolbar()
+        .insert(StateScoped(Screen::Editor))
+        .with_children(|children| {
+            children.button("Plank").observe(add_plank);
+            children.button("Prop").observe(add_prop);
+            children.button("Saw").observe(add_saw);
+            children.button("Duplicate").observe(duplicate_selected);
+            #[cfg(not(target_family = "wasm"))]
+            children.button("Save").observe(save_level);
+            children.button("Test play").observe(test_play);
+        });
+    commands.label(HELP_TEXT).insert((
+        StateScoped(Screen::Editor),
+        Style {
+            position_type: PositionType::Absolute,
+            left: Px(10.0),
+            bottom: Px(10.0),
+            ..default()
+        },
+    ));
+}
+
+/// Stops anything in the editor from falling or being pushed around.
+fn freeze_bodies(mut bodies: Query<&mut RigidBody, Added<RigidBody>>) {
+    for mut body in &mut bodies {
+        *body = RigidBody::Static;
+    }
+}
+
+fn pick_and_drag(
+    window: Query<&Window>,
+    camera: Query<(&Camera, &GlobalTransform)>,
+    input: Res<ButtonInput<MouseButton>>,
+    buttons: Query<&Interaction>,
+    mut editable: ParamSet<(
+        Query<(Entity, &Collider, &Position, &Rotation), Or<(With<Selectable>, With<SawBody>, With<Prop>)>>,
+        Query<(Entity, &mut Position, &Rotation, Has<SawBody>), With<EditorSelected>>,
+    )>,
+    mut drag: ResMut<EditorDrag>,
+    mut commands: Commands,
+) {
+    if input.just_released(MouseButton::Left) {
+        drag.0 = None;
+    }
+    let window = window.get_single().unwrap();
+    let (camera, gt) = camera.get_single().unwrap();
+    let Some(cursor) = get_world_pos(window, camera, gt).map(|p| p.truncate()) else {
+        return;
+    };
+    if input.just_pressed(MouseButton::Left) {
+        // Clicks on the toolbar shouldn't change the selection.
+        if buttons.iter().any(|i| *i != Interaction::None) {
+            return;
+        }
+        for (e, ..) in &editable.p1() {
+            commands.entity(e).remove::<EditorSelected>();
+        }
+        drag.0 = None;
+        if let Some(&(e, local)) = pick_at(cursor, &editable.p0()).first() {
+            commands.entity(e).insert(EditorSelected);
+            drag.0 = Some(local);
+        }
+        return;
+    }
+    let Some(local) = drag.0 else {
+        return;
+    };
+    if let Ok((_, mut position, rotation, is_saw)) = editable.p1().get_single_mut() {
+        let target = cursor - *rotation * local;
+        // The saw only slides along its rail.
+        position.0 = if is_saw { Vec2::new(target.x, position.y) } else { target };
+    }
+}
+
+fn edit_selected(
+    input: Res<ButtonInput<KeyCode>>,
+    mut selected: Query<
+        (
+            Entity,
+            &mut Rotation,
+            &mut Transform,
+            Option<&mut PlankShape>,
+            Option<&mut ColorInfo>,
+            Option<&mut PlankSpecies>,
+            Option<&mut Grain>,
+            Option<&mut Mesh2dHandle>,
+            Option<&Handle<ColorMaterial>>,
+            Option<&mut Prop>,
+            Option<&Children>,
+            Has<SawBody>,
+        ),
+        With<EditorSelected>,
+    >,
+    mut prop_parts: Query<(&mut PropPart, &Handle<ColorMaterial>)>,
+    saws: Query<Entity, With<Saw>>,
+    wood: Res<WoodAssets>,
+    species: Res<Assets<WoodSpecies>>,
+    mut meshes: ResMut<Assets<Mesh>>,
+    mut materials: ResMut<Assets<ColorMaterial>>,
+    mut commands: Commands,
+) {
+    let Ok((
+        e,
+        mut rotation,
+        mut transform,
+        shape,
+        mut color_info,
+        plank_species,
+        grain,
+        mesh,
+        material,
+        prop,
+        children,
+        is_saw,
+    )) = selected.get_single_mut()
+    else {
+        return;
+    };
+    if input.any_just_pressed([KeyCode::Delete, KeyCode::Backspace]) {
+        commands.entity(e).despawn_recursive();
+        if is_saw {
+            for saw in &saws {
+                commands.entity(saw).despawn_recursive();
+            }
+        }
+        return;
+    }
+    if is_saw {
+        return;
+    }
+    let mut angle = 0.;
+    if input.just_pressed(KeyCode::KeyQ) {
+        angle += ROTATE_STEP;
+    }
+    if input.just_pressed(KeyCode::KeyE) {
+        angle -= ROTATE_STEP;
+    }
+    if angle != 0. {
+        *rotation = rotation.add_angle(angle.to_radians());
+    }
+
+    let mut resize = Vec2::ZERO;
+    if input.just_pressed(KeyCode::ArrowRight) {
+        resize.x += 1.;
+    }
+    if input.just_pressed(KeyCode::ArrowLeft) {
+        resize.x -= 1.;
+    }
+    if input.just_pressed(KeyCode::ArrowUp) {
+        resize.y += 1.;
+    }
+    if input.just_pressed(KeyCode::ArrowDown) {
+        resize.y -= 1.;
+    }
+    match (shape, grain, mesh) {
+        (Some(mut shape), Some(mut grain), Some(mut mesh)) => {
+            if resize != Vec2::ZERO {
+                let size = (shape.size() + resize * Vec2::new(LENGTH_STEP, THICKNESS_STEP))
+                    .max(Vec2::splat(MIN_PLANK_SIZE));
+                *shape = PlankShape::rectangle(size.x, size.y);
+                if let Some(collider) = shape.collider() {
+                    commands.entity(e).insert(collider);
+                }
+            }
+            // Grain runs either along the plank or across it.
+            let turn_grain = input.just_pressed(KeyCode::KeyG);
+            if turn_grain {
+                grain.0 = (grain.0 + std::f32::consts::FRAC_PI_2) % std::f32::consts::PI;
+            }
+            if resize != Vec2::ZERO || turn_grain {
+                *mesh = Mesh2dHandle(meshes.add(shape.mesh(grain.direction())));
+            }
+        }
+        // Props are resized by scaling them as a whole.
+        _ => {
+            if resize.x + resize.y != 0. {
+                transform.scale *= SCALE_STEP.powf(resize.x + resize.y);
+            }
+        }
+    }
+
+    let mut new_color = None;
+    if input.just_pressed(KeyCode::KeyC) {
+        if let Some(color_info) = &color_info {
+            let next = PLANK_COLORS
+                .iter()
+                .position(|c| *c == color_info.0)
+                .map_or(0, |i| (i + 1) % PLANK_COLORS.len());
+            new_color = Some(PLANK_COLORS[next]);
+        }
+        // Props with an image keep its colours.
+        if let Some(mut prop) = prop.filter(|prop| prop.0.image.is_none()) {
+            let next = PROP_COLORS
+                .iter()
+                .position(|c| Some(*c) == prop.0.color)
+                .map_or(0, |i| (i + 1) % PROP_COLORS.len());
+            let [r, g, b] = PROP_COLORS[next];
+            prop.0.color = Some([r, g, b]);
+            for child in children.into_iter().flatten() {
+                if let Ok((mut part, material)) = prop_parts.get_mut(*child) {
+                    part.color = Color::srgb(r, g, b);
+                    if let Some(material) = materials.get_mut(material) {
+                        material.color = part.color;
+                    }
+                }
+            }
+        }
+    }
+    let mut new_grain = None;
+    if input.just_pressed(KeyCode::KeyW) {
+        if let Some(mut plank_species) = plank_species {
+            let next = wood
+                .species
+                .iter()
+                .position(|s| *s == plank_species.0)
+                .map_or(0, |i| (i + 1) % wood.species.len());
+            plank_species.0 = wood.species[next].clone();
+            // Show the new wood in its lightest colour.
+            if let Some(wood) = species.get(&plank_species.0) {
+                let [r, g, b] = wood.colors[1];
+                new_color = Some(Color::srgb(r, g, b));
+                new_grain = Some(wood.grain_handle.clone());
+            }
+        }
+    }
+    if let (Some(color), Some(color_info), Some(material)) = (new_color, &mut color_info, material) {
+        color_info.0 = color;
+        if let Some(material) = materials.get_mut(material) {
+            material.color = color;
+            if let Some(texture) = new_grain {
+                material.texture = Some(texture);
+            }
+        }
+    }
+}
+
+/// Keeps the blade on the saw body as the body is dragged.
+fn follow_saw_body(
+    body: Query<&Position, (With<SawBody>, Changed<Position>)>,
+    mut saw: Query<&mut Position, (With<Saw>, Without<SawBody>)>,
+) {
+    let (Ok(body), Ok(mut saw)) = (body.get_single(), saw.get_single_mut()) else {
+        return;
+    };
+    saw.x = body.x;
+}
+
+fn highlight_selected(selected: Query<&Position, With<EditorSelected>>, mut gizmos: Gizmos) {
+    for position in &selected {
+        gizmos.circle_2d(position.0, 4., HIGHLIGHT_COLOR);
+    }
+}
+
+fn add_plank(_trigger: Trigger<OnPress>, wood: Res<WoodAssets>, mut commands: Commands) {
+    commands.trigger(SpawnPlank {
+        shape: PlankShape::rectangle(NEW_PLANK_SIZE.x, NEW_PLANK_SIZE.y),
+        position: NEW_PLANK_POSITION,
+        rotation: Rotation::default(),
+        l_vel: None,
+        a_vel: None,
+        color: PLANK_COLORS[0],
+        species: wood.species[0].clone(),
+        grain: Grain::default(),
+        joints: Vec::new(),
+        origin: None,
+    });
+}
+
+fn add_prop(_trigger: Trigger<OnPress>, mut commands: Commands) {
+    let def = PropDef {
+        name: "Box".into(),
+        image: None,
+        color: Some(PROP_COLORS[0]),
+        position: NEW_PLANK_POSITION,
+        rotation: 0.,
+        scale: 1.,
+        mass: NEW_PROP_MASS,
+        colliders: vec![ColliderDef {
+            offset: Vec2::ZERO,
+            rotation: 0.,
+            shape: ShapeDef::Rectangle {
+                width: NEW_PROP_SIZE,
+                height: NEW_PROP_SIZE,
+            },
+        }],
+        selectable: false,
+        image_handle: default(),
+    };
+    spawn_prop(&mut commands, &def, Screen::Editor);
+}
+
+fn add_saw(_trigger: Trigger<OnPress>, saws: Query<(), With<SawBody>>, mut commands: Commands) {
+    if saws.is_empty() {
+        spawn_saw(&mut commands, &SawDef { x: 0. }, Screen::Editor);
+    }
+}
+
+fn duplicate_selected(
+    _trigger: Trigger<OnPress>,
+    planks: Query<(&PlankShape, &Position, &Rotation, &ColorInfo, &PlankSpecies, &Grain), With<EditorSelected>>,
+    props: Query<(&Prop, &Position, &Rotation, &Transform), With<EditorSelected>>,
+    mut commands: Commands,
+) {
+    for (shape, position, rotation, color_info, species, grain) in &planks {
+        commands.trigger(SpawnPlank {
+            shape: shape.clone(),
+            position: position.0 + DUPLICATE_OFFSET,
+            rotation: *rotation,
+            l_vel: None,
+            a_vel: None,
+            color: color_info.0,
+            species: species.0.clone(),
+            grain: *grain,
+            joints: Vec::new(),
+            origin: None,
+        });
+    }
+    for (prop, position, rotation, transform) in &props {
+        let mut def = prop_def(prop, position, rotation, transform);
+        def.position += DUPLICATE_OFFSET;
+        spawn_prop(&mut commands, &def, Screen::Editor);
+    }
+}
+
+fn prop_def(prop: &Prop, position: &Position, rotation: &Rotation, transform: &Transform) -> PropDef {
+    PropDef {
+        position: position.0,
+        rotation: rotation.as_radians().to_degrees(),
+        scale: transform.scale.x,
+        ..prop.0.clone()
+    }
+}
+
+/// The layout being edited, as a level.
+#[derive(SystemParam)]
+struct EditedLevel<'w, 's> {
+    planks: Query<
+        'w,
+        's,
+        (
+            &'static PlankShape,
+            &'static Position,
+            &'static Rotation,
+            &'static ColorInfo,
+            &'static PlankSpecies,
+            &'static Grain,
+        ),
+        With<Plank>,
+    >,
+    props: Query<'w, 's, (&'static Prop, &'static Position, &'static Rotation, &'static Transform)>,
+    saw: Query<'w, 's, &'static Position, With<SawBody>>,
+}
+
+impl EditedLevel<'_, '_> {
+    /// Builds the level, keeping the ground, fasteners, goals, blueprint, load test and order of
+    /// `base`.
+    fn to_level(&self, base: &LevelDef) -> LevelDef {
+        LevelDef {
+            ground: base.ground,
+            planks: self
+                .planks
+                .iter()
+                .map(|(shape, position, rotation, color_info, species, grain)| {
+                    let color = color_info.0.to_srgba();
+                    PlankDef {
+                        position: position.0,
+                        size: shape.size(),
+                        rotation: rotation.as_radians().to_degrees(),
+                        color: Some([color.red, color.green, color.blue]),
+                        species: species.0.path().map(|p| p.to_string()),
+                        species_handle: species.0.clone(),
+                        grain: grain.0.to_degrees(),
+                    }
+                })
+                .collect(),
+            pile: None,
+            props: self
+                .props
+                .iter()
+                .map(|(prop, position, rotation, transform)| prop_def(prop, position, rotation, transform))
+                .collect(),
+            saw: self.saw.get_single().ok().map(|p| SawDef { x: p.x }),
+            fasteners: base.fasteners.clone(),
+            goals: base.goals.clone(),
+            blueprint: base.blueprint.clone(),
+            blueprint_handle: base.blueprint_handle.clone(),
+            load_test: base.load_test.clone(),
+            order: base.order.clone(),
+        }
+    }
+}
+
+/// The file of the level being edited, relative to the assets folder. Levels made for a test
+/// play have no file, so the file of the level they were made from is kept.
+#[cfg(not(target_family = "wasm"))]
+#[derive(Resource, Default)]
+struct EditedLevelPath(Option<std::path::PathBuf>);
+
+#[cfg(not(target_family = "wasm"))]
+fn remember_level_path(current: Res<CurrentLevel>, mut edited: ResMut<EditedLevelPath>) {
+    if let Some(path) = current.0.path() {
+        edited.0 = Some(path.path().to_path_buf());
+    }
+}
+
+/// Where the editor saves levels that don't have a file yet, relative to the assets folder.
+#[cfg(not(target_family = "wasm"))]
+const SAVE_PATH: &str = "levels/editor.level.ron";
+
+#[cfg(not(target_family = "wasm"))]
+fn save_level(
+    _trigger: Trigger<OnPress>,
+    edited: EditedLevel,
+    current: Res<CurrentLevel>,
+    levels: Res<Assets<LevelDef>>,
+    edited_path: Res<EditedLevelPath>,
+) {
+    let Some(base) = levels.get(&current.0) else {
+        warn!("The level being edited is not loaded");
+        return;
+    };
+    let level = edited.to_level(base);
+    // Saves go back to the level's own file.
+    let path = bevy::asset::io::file::FileAssetReader::get_base_path()
+        .join("assets")
+        .join(edited_path.0.as_deref().unwrap_or(std::path::Path::new(SAVE_PATH)));
+    let result = ron::ser::to_string_pretty(&level, ron::ser::PrettyConfig::default())
+        .map_err(std::io::Error::other)
+        .and_then(|ron| std::fs::write(&path, ron));
+    match result {
+        Ok(()) => info!("Saved level to {}", path.display()),
+        Err(e) => error!("Could not save level to {}: {e}", path.display()),
+    }
+}
+
+/// Plays the layout as it is, without saving it.
+fn test_play(
+    _trigger: Trigger<OnPress>,
+    edited: EditedLevel,
+    current: Res<CurrentLevel>,
+    mut levels: ResMut<Assets<LevelDef>>,
+    mut next_screen: ResMut<NextState<Screen>>,
+    mut commands: Commands,
+) {
+    let Some(base) = levels.get(&current.0) else {
+        warn!("The level being edited is not loaded");
+        return;
+    };
+    let level = edited.to_level(base);
+    commands.insert_resource(CurrentLevel(levels.add(level)));
+    next_screen.set(Screen::Gameplay);
+}
@@ -0,0 +1,495 @@
+//! Exporting a build, so it can be built for real.
+//!
+//! The cut list names every part of the build with its size, wood and the stock board it is cut
+//! from, and comes with a shopping list of the boards to buy. Both are exported as CSV, along
+//! with a printable SVG layout of the parts on their boards. `P` exports a dimensioned SVG
+//! drawing of the build as it stands, with its fasteners. Native builds write exports to the
+//! user data directory, web builds download them.
+
+use crate::demo::blueprint::{main_build, BlueprintProgress};
+use crate::demo::fastener::{Fastener, FastenerKind};
+use crate::demo::joint::{DrivenFastener, PlankJoint};
+use crate::demo::level::Plank;
+use crate::demo::lineage::{cut_history, PlankLineage, StockCuts};
+use crate::demo::shape::PlankShape;
+use crate::demo::wood::WoodSpecies;
+use crate::screens::Screen;
+use avian2d::position::{Position, Rotation};
+use bevy::input::common_conditions::input_just_pressed;
+use bevy::prelude::*;
+use std::borrow::Cow;
+use std::collections::BTreeMap;
+use std::fmt::Write;
+
+pub(super) fn plugin(app: &mut App) {
+    app.add_systems(
+        Update,
+        export_drawing.run_if(in_state(Screen::Gameplay).and_then(input_just_pressed(KeyCode::KeyP))),
+    );
+}
+
+#[derive(Debug, thiserror::Error)]
+pub enum ExportError {
+    #[cfg(not(target_family = "wasm"))]
+    #[error("could not write export: {0}")]
+    Io(#[from] std::io::Error),
+    #[error("no place to export to: {0}")]
+    Storage(String),
+}
+
+/// Writes an export called `file_name`, returning where it went.
+#[cfg(not(target_family = "wasm"))]
+pub fn write_export(file_name: &str, contents: &[u8]) -> Result<String, ExportError> {
+    let dir = dirs::data_dir()
+        .ok_or_else(|| ExportError::Storage("no user data directory".to_string()))?
+        .join("custom_furniture")
+        .join("exports");
+    std::fs::create_dir_all(&dir)?;
+    let path = dir.join(file_name);
+    std::fs::write(&path, contents)?;
+    Ok(path.display().to_string())
+}
+
+/// Downloads an export called `file_name`, returning where it went.
+#[cfg(target_family = "wasm")]
+pub fn write_export(file_name: &str, contents: &[u8]) -> Result<String, ExportError> {
+    use wasm_bindgen::JsCast;
+
+    let storage_error = |e| ExportError::Storage(format!("{e:?}"));
+    let document = web_sys::window()
+        .and_then(|w| w.document())
+        .ok_or_else(|| ExportError::Storage("the page has no document".to_string()))?;
+    let link: web_sys::HtmlAnchorElement = document
+        .create_element("a")
+        .map_err(storage_error)?
+        .unchecked_into();
+    // A data URL saves pulling in the whole Blob API for the odd download.
+    let mut url = "data:application/octet-stream,".to_string();
+    for byte in contents {
+        let _ = write!(url, "%{byte:02X}");
+    }
+    link.set_href(&url);
+    link.set_download(file_name);
+    link.click();
+    Ok(format!("downloads as {file_name}"))
+}
+
+/// A part of the build, as it has to be cut.
+#[derive(Clone, Debug)]
+pub struct CutListPart {
+    pub shape: PlankShape,
+    pub species: String,
+    /// Number of the stock board the part is cut from.
+    pub stock: usize,
+    /// Where the part's centre is on its stock board, in the board's local frame.
+    pub offset: Vec2,
+}
+
+/// The cut list of a build.
+#[derive(Clone, Debug, Default)]
+pub struct CutList {
+    pub parts: Vec<CutListPart>,
+    /// The stock boards the parts are cut from, by number.
+    pub stock: BTreeMap<usize, StockCuts>,
+}
+
+impl CutList {
+    /// The cut list of the planks of a build, each with its lineage and species.
+    pub fn new<'a>(
+        planks: impl IntoIterator<Item = (&'a PlankShape, &'a PlankLineage, Option<&'a WoodSpecies>)>,
+    ) -> Self {
+        let planks: Vec<_> = planks.into_iter().collect();
+        let parts = planks
+            .iter()
+            .map(|(shape, lineage, species)| CutListPart {
+                shape: (*shape).clone(),
+                species: species.map_or_else(|| WoodSpecies::default().name, |s| s.name.clone()),
+                stock: lineage.stock,
+                offset: lineage.offset,
+            })
+            .collect();
+        let stock = cut_history(planks.iter().map(|(_, lineage, species)| (*lineage, *species)));
+        Self { parts, stock }
+    }
+
+    /// One row per part: its number, size, wood and stock board.
+    pub fn to_csv(&self) -> String {
+        let mut csv = "Part,Length,Width,Species,Stock board\n".to_string();
+        for (i, part) in self.parts.iter().enumerate() {
+            let size = part.shape.size();
+            let _ = writeln!(
+                csv,
+                "{},{:.1},{:.1},{},{}",
+                i + 1,
+                size.x,
+                size.y,
+                csv_field(&part.species),
+                part.stock + 1
+            );
+        }
+        csv
+    }
+
+    /// The stock boards to buy, with boards of the same wood and size counted together.
+    pub fn shopping_list(&self) -> Vec<(usize, String, Vec2)> {
+        let mut list: Vec<(usize, String, Vec2)> = Vec::new();
+        for stock in self.stock.values() {
+            let size = stock.size.round();
+            match list.iter_mut().find(|(_, species, s)| *species == stock.species && *s == size) {
+                Some((count, ..)) => *count += 1,
+                None => list.push((1, stock.species.clone(), size)),
+            }
+        }
+        list
+    }
+
+    pub fn shopping_list_csv(&self) -> String {
+        let mut csv = "Quantity,Species,Length,Width\n".to_string();
+        for (count, species, size) in self.shopping_list() {
+            let _ = writeln!(csv, "{count},{},{:.0},{:.0}", csv_field(&species), size.x, size.y);
+        }
+        csv
+    }
+
+    /// A printable layout: every stock board with its parts drawn where they are cut from,
+    /// followed by the shopping list.
+    pub fn to_svg(&self) -> String {
+        let width = self.stock.values().map(|s| s.size.x).fold(0., f32::max) * SVG_SCALE;
+        let mut body = String::new();
+        let mut y = SVG_MARGIN;
+        for (number, stock) in &self.stock {
+            let size = stock.size * SVG_SCALE;
+            let _ = writeln!(
+                body,
+                r#"<text x="{SVG_MARGIN}" y="{:.1}">Board {}: {}, {:.0} x {:.0}</text>"#,
+                y + SVG_LINE,
+                number + 1,
+                xml_text(&stock.species),
+                stock.size.x,
+                stock.size.y
+            );
+            y += SVG_LINE * 1.5;
+            let _ = writeln!(
+                body,
+                r#"<rect x="{SVG_MARGIN}" y="{y:.1}" width="{:.1}" height="{:.1}" fill="none" stroke="black" stroke-dasharray="4 2"/>"#,
+                size.x, size.y
+            );
+            // The board's centre, with y pointing down as SVG has it.
+            let center = Vec2::new(SVG_MARGIN, y) + size / 2.;
+            let to_svg = |v: Vec2| center + Vec2::new(v.x, -v.y) * SVG_SCALE;
+            for (i, part) in self.parts.iter().enumerate().filter(|(_, p)| p.stock == *number) {
+                for polygon in part.shape.parts() {
+                    let points: Vec<String> = polygon
+                        .iter()
+                        .map(|v| to_svg(part.offset + *v))
+                        .map(|p| format!("{:.1},{:.1}", p.x, p.y))
+                        .collect();
+                    let _ = writeln!(body, r#"<polygon points="{}" fill="{PART_FILL}"/>"#, points.join(" "));
+                }
+                let label = to_svg(part.offset);
+                let _ = writeln!(
+                    body,
+                    r#"<text x="{:.1}" y="{:.1}" text-anchor="middle" dominant-baseline="middle">{}</text>"#,
+                    label.x,
+                    label.y,
+                    i + 1
+                );
+            }
+            y += size.y + SVG_LINE;
+        }
+        let _ = writeln!(
+            body,
+            r#"<text x="{SVG_MARGIN}" y="{:.1}">Shopping list</text>"#,
+            y + SVG_LINE
+        );
+        y += SVG_LINE * 1.5;
+        for (count, species, size) in self.shopping_list() {
+            y += SVG_LINE;
+            let _ = writeln!(
+                body,
+                r#"<text x="{SVG_MARGIN}" y="{y:.1}">{count} x {}, {:.0} x {:.0}</text>"#,
+                xml_text(&species),
+                size.x,
+                size.y
+            );
+        }
+        let width = width.max(SVG_MIN_WIDTH) + SVG_MARGIN * 2.;
+        let height = y + SVG_MARGIN;
+        format!(
+            concat!(
+                r#"<svg xmlns="http://www.w3.org/2000/svg" width="{w:.0}" height="{h:.0}" viewBox="0 0 {w:.0} {h:.0}" "#,
+                r#"font-family="sans-serif" font-size="12">"#,
+                "\n{body}</svg>\n"
+            ),
+            w = width,
+            h = height,
+            body = body
+        )
+    }
+
+    /// Writes the cut list, shopping list and layout, returning where they went.
+    pub fn export(&self) -> Result<Vec<String>, ExportError> {
+        Ok(vec![
+            write_export("cut_list.csv", self.to_csv().as_bytes())?,
+            write_export("shopping_list.csv", self.shopping_list_csv().as_bytes())?,
+            write_export("cut_list.svg", self.to_svg().as_bytes())?,
+        ])
+    }
+}
+
+/// A CSV field, quoted when it holds a comma, quote or line break.
+fn csv_field(field: &str) -> Cow<str> {
+    if field.contains([',', '"', '\n', '\r']) {
+        format!("\"{}\"", field.replace('"', "\"\"")).into()
+    } else {
+        field.into()
+    }
+}
+
+/// Text with the characters that have a meaning in XML escaped.
+fn xml_text(text: &str) -> Cow<str> {
+    if !text.contains(['&', '<', '>', '"', '\'']) {
+        return text.into();
+    }
+    let mut escaped = String::with_capacity(text.len());
+    for c in text.chars() {
+        match c {
+            '&' => escaped.push_str("&amp;"),
+            '<' => escaped.push_str("&lt;"),
+            '>' => escaped.push_str("&gt;"),
+            '"' => escaped.push_str("&quot;"),
+            '\'' => escaped.push_str("&apos;"),
+            c => escaped.push(c),
+        }
+    }
+    escaped.into()
+}
+
+/// SVG pixels per game unit in the layout.
+const SVG_SCALE: f32 = 4.;
+const SVG_MARGIN: f32 = 20.;
+/// Height of a line of text in the layout.
+const SVG_LINE: f32 = 16.;
+/// Leaves room for the text when the boards are short.
+const SVG_MIN_WIDTH: f32 = 300.;
+const PART_FILL: &str = "#d9b38c";
+
+/// A plank in a [`BuildDrawing`].
+#[derive(Clone, Debug)]
+pub struct DrawnPlank {
+    /// Edges of the outline, in world space.
+    pub outline: Vec<(Vec2, Vec2)>,
+    pub center: Vec2,
+    pub size: Vec2,
+}
+
+/// A fastener in a [`BuildDrawing`].
+#[derive(Clone, Copy, Debug)]
+pub struct DrawnFastener {
+    pub kind: FastenerKind,
+    pub position: Vec2,
+    /// Direction the fastener was driven in.
+    pub direction: Vec2,
+}
+
+/// A dimensioned drawing of a build, made from its planks and fasteners rather than the screen,
+/// so it doesn't need a window.
+#[derive(Clone, Debug, Default)]
+pub struct BuildDrawing {
+    pub planks: Vec<DrawnPlank>,
+    pub fasteners: Vec<DrawnFastener>,
+}
+
+impl BuildDrawing {
+    /// A drawing of planks and fasteners where they are in the world.
+    pub fn new<'a>(
+        planks: impl IntoIterator<Item = (&'a PlankShape, Vec2, Rotation)>,
+        fasteners: impl IntoIterator<Item = (FastenerKind, Vec2, Rotation)>,
+    ) -> Self {
+        let planks = planks
+            .into_iter()
+            .map(|(shape, position, rotation)| DrawnPlank {
+                outline: shape
+                    .outline()
+                    .map(|(a, b)| (position + rotation * a, position + rotation * b))
+                    .collect(),
+                center: position,
+                size: shape.size(),
+            })
+            .collect();
+        let fasteners = fasteners
+            .into_iter()
+            .map(|(kind, position, rotation)| DrawnFastener {
+                kind,
+                position,
+                direction: rotation * Vec2::Y,
+            })
+            .collect();
+        Self { planks, fasteners }
+    }
+
+    /// Corners of the box around the planks.
+    fn bounds(&self) -> (Vec2, Vec2) {
+        self.planks
+            .iter()
+            .flat_map(|p| p.outline.iter().flat_map(|(a, b)| [*a, *b]))
+            .fold(
+                (Vec2::splat(f32::INFINITY), Vec2::splat(f32::NEG_INFINITY)),
+                |(min, max), v| (min.min(v), max.max(v)),
+            )
+    }
+
+    /// The drawing as SVG: the outlines with each plank's size, the fasteners numbered, the
+    /// build's overall size along the bottom and left, and where each fastener is measured from
+    /// the bottom left corner.
+    pub fn to_svg(&self) -> String {
+        let (min, max) = self.bounds();
+        let size = (max - min).max(Vec2::ZERO);
+        let drawn = size * SVG_SCALE;
+        // Room for the overall dimensions left of and below the build.
+        let origin = Vec2::splat(SVG_MARGIN + DIMENSION_SPACE);
+        let to_svg = |v: Vec2| origin + Vec2::new(v.x - min.x, max.y - v.y) * SVG_SCALE;
+        let mut body = String::new();
+
+        for plank in &self.planks {
+            for (a, b) in &plank.outline {
+                let (a, b) = (to_svg(*a), to_svg(*b));
+                let _ = writeln!(
+                    body,
+                    r#"<line x1="{:.1}" y1="{:.1}" x2="{:.1}" y2="{:.1}" stroke="black"/>"#,
+                    a.x, a.y, b.x, b.y
+                );
+            }
+            let center = to_svg(plank.center);
+            let _ = writeln!(
+                body,
+                r#"<text x="{:.1}" y="{:.1}" text-anchor="middle" dominant-baseline="middle">{:.1} x {:.1}</text>"#,
+                center.x, center.y, plank.size.x, plank.size.y
+            );
+        }
+        for (i, fastener) in self.fasteners.iter().enumerate() {
+            let spec = fastener.kind.spec();
+            let half = fastener.direction * spec.length / 2.;
+            let (a, b) = (to_svg(fastener.position - half), to_svg(fastener.position + half));
+            let p = to_svg(fastener.position);
+            let _ = writeln!(
+                body,
+                r#"<line x1="{:.1}" y1="{:.1}" x2="{:.1}" y2="{:.1}" stroke="{FASTENER_STROKE}" stroke-width="{:.1}"/>"#,
+                a.x,
+                a.y,
+                b.x,
+                b.y,
+                spec.width * SVG_SCALE
+            );
+            let _ = writeln!(
+                body,
+                r#"<text x="{:.1}" y="{:.1}" fill="{FASTENER_STROKE}" font-size="10">F{}</text>"#,
+                p.x + 4.,
+                p.y - 4.,
+                i + 1
+            );
+        }
+
+        // Overall width along the bottom, overall height along the left.
+        let bottom = origin.y + drawn.y + DIMENSION_SPACE / 2.;
+        let left = origin.x - DIMENSION_SPACE / 2.;
+        let _ = writeln!(
+            body,
+            r#"<line x1="{:.1}" y1="{bottom:.1}" x2="{:.1}" y2="{bottom:.1}" stroke="black" marker-start="url(#tick)" marker-end="url(#tick)"/>"#,
+            origin.x,
+            origin.x + drawn.x
+        );
+        let _ = writeln!(
+            body,
+            r#"<text x="{:.1}" y="{:.1}" text-anchor="middle">{:.1}</text>"#,
+            origin.x + drawn.x / 2.,
+            bottom + SVG_LINE,
+            size.x
+        );
+        let _ = writeln!(
+            body,
+            r#"<line x1="{left:.1}" y1="{:.1}" x2="{left:.1}" y2="{:.1}" stroke="black" marker-start="url(#tick)" marker-end="url(#tick)"/>"#,
+            origin.y,
+            origin.y + drawn.y
+        );
+        let middle = origin.y + drawn.y / 2.;
+        let _ = writeln!(
+            body,
+            r#"<text x="{:.1}" y="{middle:.1}" text-anchor="middle" transform="rotate(-90 {:.1} {middle:.1})">{:.1}</text>"#,
+            left - SVG_LINE / 2.,
+            left - SVG_LINE / 2.,
+            size.y
+        );
+
+        let mut y = bottom + SVG_LINE * 2.5;
+        if !self.fasteners.is_empty() {
+            let _ = writeln!(
+                body,
+                r#"<text x="{SVG_MARGIN}" y="{y:.1}">Fasteners, from the bottom left corner</text>"#
+            );
+        }
+        for (i, fastener) in self.fasteners.iter().enumerate() {
+            y += SVG_LINE;
+            let from_corner = fastener.position - min;
+            let _ = writeln!(
+                body,
+                r#"<text x="{SVG_MARGIN}" y="{y:.1}">F{}: {} at {:.1}, {:.1}</text>"#,
+                i + 1,
+                xml_text(fastener.kind.spec().name),
+                from_corner.x,
+                from_corner.y
+            );
+        }
+
+        let width = (origin.x + drawn.x).max(SVG_MIN_WIDTH) + SVG_MARGIN;
+        let height = y + SVG_MARGIN;
+        format!(
+            concat!(
+                r#"<svg xmlns="http://www.w3.org/2000/svg" width="{w:.0}" height="{h:.0}" viewBox="0 0 {w:.0} {h:.0}" "#,
+                r#"font-family="sans-serif" font-size="12">"#,
+                "\n",
+                r#"<defs><marker id="tick" viewBox="0 0 2 10" refX="1" refY="5" markerWidth="2" markerHeight="10" orient="auto">"#,
+                r#"<line x1="1" y1="0" x2="1" y2="10" stroke="black"/></marker></defs>"#,
+                "\n{body}</svg>\n"
+            ),
+            w = width,
+            h = height,
+            body = body
+        )
+    }
+}
+
+/// Room for a dimension line and its measurement.
+const DIMENSION_SPACE: f32 = 30.;
+const FASTENER_STROKE: &str = "#b03030";
+
+fn export_drawing(
+    progress: Res<BlueprintProgress>,
+    planks: Query<(Entity, &PlankShape, &Position, &Rotation), With<Plank>>,
+    joints: Query<&PlankJoint>,
+    fasteners: Query<(&Fastener, &DrivenFastener)>,
+) {
+    let build = main_build(&progress, planks.iter().map(|(e, ..)| e), joints.iter().map(|j| j.planks));
+    if build.is_empty() {
+        warn!("There is no build to draw");
+        return;
+    }
+    let drawing = BuildDrawing::new(
+        planks.iter_many(&build).map(|(_, shape, position, rotation)| (shape, position.0, *rotation)),
+        fasteners.iter().filter_map(|(fastener, driven)| {
+            if !build.contains(&driven.plank) {
+                return None;
+            }
+            let (_, _, position, rotation) = planks.get(driven.plank).ok()?;
+            Some((
+                fastener.kind,
+                position.0 + *rotation * driven.local_position,
+                rotation.add_angle(driven.local_angle),
+            ))
+        }),
+    );
+    match write_export("build.svg", drawing.to_svg().as_bytes()) {
+        Ok(path) => info!("Exported a drawing of the build to {path}"),
+        Err(e) => error!("Could not export a drawing of the build: {e}"),
+    }
+}
@@ -0,0 +1,229 @@
+//! The catalogue of fasteners that join planks, and the tool for driving them.
+
+use crate::demo::history::BuildAction;
+use crate::demo::joint::{fastener_fits, DriveFastener};
+use crate::demo::level::{CurrentLevel, LevelDef, Plank};
+use crate::demo::shape::PlankShape;
+use crate::demo::{get_world_pos, BuildPhase, HeldTool};
+use crate::screens::Screen;
+use crate::theme::prelude::*;
+use avian2d::collision::Collider;
+use avian2d::position::{Position, Rotation};
+use avian2d::prelude::{CollidingEntities, Sensor};
+use bevy::prelude::*;
+use serde::{Deserialize, Serialize};
+
+pub(super) fn plugin(app: &mut App) {
+    app.init_resource::<SelectedFastener>();
+    app.add_systems(OnEnter(Screen::Gameplay), spawn_toolbar);
+    app.add_systems(Update, fastener_system.run_if(in_state(BuildPhase::Building)));
+}
+
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq, PartialOrd, Ord, Hash, Serialize, Deserialize)]
+pub enum FastenerKind {
+    #[default]
+    Nail,
+    Screw,
+    Dowel,
+    Bolt,
+}
+
+impl FastenerKind {
+    pub const ALL: [FastenerKind; 4] = [Self::Nail, Self::Screw, Self::Dowel, Self::Bolt];
+
+    pub fn spec(self) -> &'static FastenerSpec {
+        &FASTENERS[self as usize]
+    }
+}
+
+/// How fasteners of a kind hold two planks together.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum FastenerHold {
+    /// One lets the planks pivot around it, two or more hold them rigidly.
+    Pin,
+    /// Holds the planks rigidly on its own.
+    Rigid,
+    /// Always lets the planks pivot, no matter how many there are.
+    Hinge,
+}
+
+/// What a kind of fastener is like, and the rules for placing it.
+pub struct FastenerSpec {
+    pub name: &'static str,
+    /// Length of the fastener, which is also the length of its collider.
+    pub length: f32,
+    pub width: f32,
+    pub hold: FastenerHold,
+    /// Force one fastener holds with a nominal overlap of wood around it.
+    pub strength: f32,
+    /// Compliance of joints made with this fastener, i.e. how much they give under load.
+    pub compliance: f32,
+    pub cost: u32,
+    /// Whether the fastener has to go into a pre-drilled hole in every plank it joins.
+    pub needs_hole: bool,
+    pub color: Color,
+}
+
+/// All fastener kinds, in the order of [`FastenerKind`].
+const FASTENERS: [FastenerSpec; 4] = [
+    FastenerSpec {
+        name: "Nail",
+        length: 10.,
+        width: 1.,
+        hold: FastenerHold::Pin,
+        strength: 4e5,
+        compliance: 1e-7,
+        cost: 1,
+        needs_hole: false,
+        color: Color::srgb(0.6, 0.6, 0.65),
+    },
+    FastenerSpec {
+        name: "Screw",
+        length: 12.,
+        width: 1.5,
+        hold: FastenerHold::Pin,
+        strength: 7e5,
+        compliance: 6e-8,
+        cost: 3,
+        needs_hole: false,
+        color: Color::srgb(0.75, 0.7, 0.45),
+    },
+    FastenerSpec {
+        name: "Dowel",
+        length: 14.,
+        width: 3.,
+        hold: FastenerHold::Rigid,
+        strength: 5e5,
+        compliance: 5e-8,
+        cost: 2,
+        needs_hole: true,
+        color: Color::srgb(0.8, 0.65, 0.4),
+    },
+    FastenerSpec {
+        name: "Bolt",
+        length: 16.,
+        width: 2.5,
+        hold: FastenerHold::Hinge,
+        strength: 1.2e6,
+        compliance: 5e-8,
+        cost: 5,
+        needs_hole: false,
+        color: Color::srgb(0.4, 0.4, 0.45),
+    },
+];
+
+/// The fastener kind picked in the toolbar.
+#[derive(Resource, Default)]
+pub struct SelectedFastener(pub FastenerKind);
+
+#[derive(Component)]
+pub struct Fastener {
+    pub kind: FastenerKind,
+}
+
+/// Marks the fastener following the cursor, ready to be driven into planks.
+#[derive(Component)]
+pub struct HeldFastener;
+
+/// Where a fastener's [`Position`] sits along its length, as a fraction from its head.
+/// Both the held fastener's collider and the driven fastener's sprite are placed this way.
+pub const FASTENER_HEAD: f32 = 0.25;
+
+fn spawn_held_fastener(commands: &mut Commands, kind: FastenerKind) {
+    let spec = kind.spec();
+    commands.spawn((
+        Name::new(format!("Held {}", spec.name)),
+        Fastener { kind },
+        HeldFastener,
+        HeldTool,
+        Transform::from_xyz(0., 200., 0.),
+        Sensor,
+        Collider::segment(
+            Vec2::Y * spec.length * FASTENER_HEAD,
+            -Vec2::Y * spec.length * (1. - FASTENER_HEAD),
+        ),
+        StateScoped(Screen::Gameplay),
+    ));
+}
+
+fn fastener_system(input: Res<ButtonInput<KeyCode>>, mut held: Query<(Entity, &Fastener, &mut Position, &mut Rotation, &CollidingEntities), With<HeldFastener>>, mut commands: Commands
+               ,
+               window: Query<&Window>, camera: Query<(&Camera, &GlobalTransform)>,
+               mouse: Res<ButtonInput<MouseButton>>,
+               planks: Query<(&PlankShape, &Position, &Rotation), (With<Plank>, Without<HeldFastener>)>,
+               tools: Query<Entity, With<HeldTool>>,
+               selected: Res<SelectedFastener>,
+) {
+    if input.just_pressed(KeyCode::KeyN) {
+        if held.is_empty() {
+            for tool in &tools {
+                commands.entity(tool).despawn();
+            }
+            spawn_held_fastener(&mut commands, selected.0);
+        } else {
+            for (e, ..) in &held {
+                commands.entity(e).despawn();
+            }
+        }
+    }
+    if let Ok((_, fastener, mut position, mut rotation, colliding)) = held.get_single_mut() {
+        let window = window.get_single().unwrap();
+        let (camera, gt) = camera.get_single().unwrap();
+        if let Some(p) = get_world_pos(window, camera, gt) {
+            position.0 = p.truncate()
+        }
+        if input.pressed(KeyCode::KeyR) {
+            *rotation = rotation.add_angle(-1f32.to_radians());
+        }
+        if mouse.just_pressed(MouseButton::Left) {
+            let hit: Vec<Entity> = colliding.0.iter().copied().filter(|e| planks.contains(*e)).collect();
+            if hit.len() >= 2 {
+                // Fasteners that don't fit are refused, so there is nothing to undo.
+                if fastener_fits(fastener.kind, &hit, position.0, &planks) {
+                    commands.trigger(BuildAction::new("Fastener", hit.iter().copied()));
+                }
+                commands.trigger(DriveFastener {
+                    kind: fastener.kind,
+                    planks: hit,
+                    position: position.0,
+                    rotation: *rotation,
+                });
+            }
+        }
+    }
+}
+
+/// Offers the fasteners the current level allows, or all of them if it isn't loaded.
+fn spawn_toolbar(
+    mut commands: Commands,
+    current: Res<CurrentLevel>,
+    levels: Res<Assets<LevelDef>>,
+    mut selected: ResMut<SelectedFastener>,
+) {
+    let kinds = levels
+        .get(&current.0)
+        .map_or(FastenerKind::ALL.to_vec(), |level| level.fasteners.clone());
+    if let Some(&first) = kinds.first().filter(|_| !kinds.contains(&selected.0)) {
+        selected.0 = first;
+    }
+    commands
+        .toolbar()
+        .insert(StateScoped(Screen::Gameplay))
+        .with_children(|children| {
+            for kind in kinds {
+                let spec = kind.spec();
+                children
+                    .button(format!("{} ${}", spec.name, spec.cost))
+                    .observe(move |_trigger: Trigger<OnPress>,
+                                   mut selected: ResMut<SelectedFastener>,
+                                   tools: Query<Entity, With<HeldTool>>,
+                                   mut commands: Commands| {
+                        selected.0 = kind;
+                        for tool in &tools {
+                            commands.entity(tool).despawn();
+                        }
+                        spawn_held_fastener(&mut commands, kind);
+                    });
+            }
+        });
+}
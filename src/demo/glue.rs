@@ -0,0 +1,294 @@
+//! Wood glue. Painted along the edge of a plank, it bonds any plank pressed against that edge
+//! once it has had time to cure.
+
+use crate::demo::history::BuildAction;
+use crate::demo::joint::BondGlue;
+use crate::demo::level::Plank;
+use crate::demo::shape::PlankShape;
+use crate::demo::{get_world_pos, BuildPhase, ColorInfo, HeldTool, OnPlankSpawned};
+use crate::screens::Screen;
+use avian2d::position::{Position, Rotation};
+use avian2d::prelude::CollidingEntities;
+use bevy::prelude::*;
+use bevy::utils::HashMap;
+
+pub(super) fn plugin(app: &mut App) {
+    app.add_systems(
+        Update,
+        (
+            (toggle_glue_brush, paint_glue).chain().run_if(in_state(BuildPhase::Building)),
+            (cure_glue, show_glue_state).chain(),
+        )
+            .chain()
+            .run_if(in_state(Screen::Gameplay)),
+    );
+    app.observe(keep_glue_on_pieces);
+}
+
+/// The glue brush following the cursor while the glue tool is in hand.
+#[derive(Component)]
+pub struct GlueBrush;
+
+/// Glue painted on a plank.
+#[derive(Component, Default, Clone)]
+pub struct Glue {
+    /// Glued points along the plank's edges, in the plank's local frame.
+    pub spots: Vec<Vec2>,
+    /// Planks pressed against the glue.
+    pub bonds: Vec<GlueBond>,
+}
+
+#[derive(Clone)]
+pub struct GlueBond {
+    pub plank: Entity,
+    /// Seconds the plank has been pressed against the glue.
+    pub elapsed: f32,
+    pub cured: bool,
+}
+
+impl Glue {
+    /// Cure progress of the furthest along bond that hasn't cured yet, between 0 and 1.
+    fn curing_progress(&self) -> Option<f32> {
+        self.bonds
+            .iter()
+            .filter(|b| !b.cured)
+            .map(|b| b.elapsed / GLUE_CURE_SECS)
+            .reduce(f32::max)
+    }
+}
+
+const GLUE_CURE_SECS: f32 = 5.;
+/// How far from a plank's edge the brush still paints, and how far a plank may be from the glue
+/// to still be pressed against it.
+const GLUE_REACH: f32 = 3.;
+/// Distance between glue spots. Each spot covers this much of the edge.
+const GLUE_SPACING: f32 = 2.;
+
+const GLUE_WET_COLOR: Color = Color::srgb(0.95, 0.95, 0.9);
+const GLUE_CURED_COLOR: Color = Color::srgb(0.85, 0.6, 0.2);
+const BRUSH_SIZE: f32 = 3.;
+
+/// Glue that hasn't bonded anything yet stays on the edges of the pieces a plank is cut into.
+fn keep_glue_on_pieces(
+    trigger: Trigger<OnPlankSpawned>,
+    planks: Query<(&PlankShape, &Position, &Rotation), With<Plank>>,
+    glue: Query<&Glue>,
+    mut commands: Commands,
+) {
+    let ev = trigger.event();
+    let Some(origin) = ev.origin else {
+        return;
+    };
+    let (Ok(glue), Ok([(_, origin_pos, origin_rot), (shape, position, rotation)])) =
+        (glue.get(origin), planks.get_many([origin, ev.plank]))
+    else {
+        return;
+    };
+    let spots: Vec<Vec2> = glue
+        .spots
+        .iter()
+        .map(|s| rotation.inverse() * (origin_pos.0 + *origin_rot * *s - position.0))
+        .filter(|s| shape.closest_boundary_point(*s).distance(*s) <= GLUE_SPACING / 2.)
+        .collect();
+    if !spots.is_empty() {
+        commands.entity(ev.plank).insert(Glue { spots, ..default() });
+    }
+}
+
+fn toggle_glue_brush(
+    input: Res<ButtonInput<KeyCode>>,
+    brushes: Query<Entity, With<GlueBrush>>,
+    tools: Query<Entity, With<HeldTool>>,
+    mut commands: Commands,
+) {
+    if !input.just_pressed(KeyCode::KeyG) {
+        return;
+    }
+    if brushes.is_empty() {
+        for tool in &tools {
+            commands.entity(tool).despawn();
+        }
+        commands.spawn((
+            Name::new("Glue brush"),
+            GlueBrush,
+            HeldTool,
+            SpriteBundle {
+                sprite: Sprite {
+                    color: GLUE_WET_COLOR,
+                    custom_size: Some(Vec2::splat(BRUSH_SIZE)),
+                    ..default()
+                },
+                transform: Transform::from_xyz(0., 200., 2.),
+                ..default()
+            },
+            StateScoped(Screen::Gameplay),
+        ));
+    } else {
+        for brush in &brushes {
+            commands.entity(brush).despawn();
+        }
+    }
+}
+
+fn paint_glue(
+    mut brush: Query<&mut Transform, With<GlueBrush>>,
+    mut planks: Query<(Entity, &PlankShape, &Position, &Rotation, Option<&mut Glue>), With<Plank>>,
+    window: Query<&Window>,
+    camera: Query<(&Camera, &GlobalTransform)>,
+    mouse: Res<ButtonInput<MouseButton>>,
+    mut stroke_recorded: Local<bool>,
+    mut commands: Commands,
+) {
+    let Ok(mut brush) = brush.get_single_mut() else {
+        return;
+    };
+    let window = window.get_single().unwrap();
+    let (camera, gt) = camera.get_single().unwrap();
+    let Some(cursor) = get_world_pos(window, camera, gt).map(|p| p.truncate()) else {
+        return;
+    };
+    brush.translation = cursor.extend(brush.translation.z);
+    if !mouse.pressed(MouseButton::Left) {
+        *stroke_recorded = false;
+        return;
+    }
+    let reachable = |shape: &PlankShape, local: Vec2| {
+        let spot = shape.closest_boundary_point(local);
+        (spot.distance(local) <= GLUE_REACH).then_some(spot)
+    };
+    // A stroke starts painting the frame after its action is recorded, so the action has the glue
+    // as it was.
+    if !*stroke_recorded {
+        let on_an_edge = planks.iter().any(|(_, shape, position, rotation, _)| {
+            reachable(shape, rotation.inverse() * (cursor - position.0)).is_some()
+        });
+        if on_an_edge {
+            commands.trigger(BuildAction::new("Glue", []));
+            *stroke_recorded = true;
+        }
+        return;
+    }
+    for (e, shape, position, rotation, glue) in &mut planks {
+        let Some(spot) = reachable(shape, rotation.inverse() * (cursor - position.0)) else {
+            continue;
+        };
+        match glue {
+            Some(mut glue) => {
+                if glue.spots.iter().all(|s| s.distance(spot) >= GLUE_SPACING) {
+                    glue.spots.push(spot);
+                }
+            }
+            None => {
+                commands.entity(e).insert(Glue {
+                    spots: vec![spot],
+                    ..default()
+                });
+            }
+        }
+    }
+}
+
+/// Times how long planks have been pressed against glue, and bonds them once the glue cures.
+fn cure_glue(
+    mut glued: Query<(Entity, &mut Glue, &Position, &Rotation, &CollidingEntities)>,
+    planks: Query<(&PlankShape, &Position, &Rotation), With<Plank>>,
+    time: Res<Time>,
+    mut commands: Commands,
+) {
+    // Whether each glued plank's bond to another has cured, by (glued plank, other plank).
+    let cured: HashMap<(Entity, Entity), bool> = glued
+        .iter()
+        .flat_map(|(e, glue, ..)| glue.bonds.iter().map(move |b| ((e, b.plank), b.cured)))
+        .collect();
+    for (e, mut glue_ref, position, rotation, colliding) in &mut glued {
+        // Only flag the glue as changed when something happened, so its tint isn't rebuilt
+        // every frame.
+        let glue = glue_ref.bypass_change_detection();
+        let bond_count = glue.bonds.len();
+        glue.bonds.retain(|b| planks.contains(b.plank));
+        let mut changed = glue.bonds.len() != bond_count;
+        for &other in colliding.0.iter() {
+            let Ok((other_shape, other_pos, other_rot)) = planks.get(other) else {
+                continue;
+            };
+            let contact: Vec<Vec2> = glue
+                .spots
+                .iter()
+                .map(|s| position.0 + *rotation * *s)
+                .filter(|s| {
+                    let local = other_rot.inverse() * (*s - other_pos.0);
+                    other_shape.contains(local)
+                        || other_shape.closest_boundary_point(local).distance(local) <= GLUE_REACH
+                })
+                .collect();
+            let bond_index = glue.bonds.iter().position(|b| b.plank == other);
+            // When both planks are glued where they touch, they bond once: through the glue that
+            // cured first, or else through the plank that comes first.
+            if let Some(&other_cured) = cured.get(&(other, e)) {
+                let own_cured = bond_index.is_some_and(|i| glue.bonds[i].cured);
+                if !own_cured && (other_cured || other < e) {
+                    if let Some(i) = bond_index {
+                        glue.bonds.remove(i);
+                        changed = true;
+                    }
+                    continue;
+                }
+            }
+            if contact.is_empty() {
+                // Uncured glue lets go when the planks are pulled apart.
+                if let Some(i) = bond_index.filter(|i| !glue.bonds[*i].cured) {
+                    glue.bonds.remove(i);
+                    changed = true;
+                }
+                continue;
+            }
+            let bond = match bond_index {
+                Some(i) => &mut glue.bonds[i],
+                None => {
+                    glue.bonds.push(GlueBond {
+                        plank: other,
+                        elapsed: 0.,
+                        cured: false,
+                    });
+                    glue.bonds.last_mut().unwrap()
+                }
+            };
+            if bond.cured {
+                continue;
+            }
+            bond.elapsed += time.delta_seconds();
+            changed = true;
+            if bond.elapsed >= GLUE_CURE_SECS {
+                bond.cured = true;
+                commands.trigger(BondGlue {
+                    planks: [e, other],
+                    position: contact.iter().sum::<Vec2>() / contact.len() as f32,
+                    length: contact.len() as f32 * GLUE_SPACING,
+                });
+            }
+        }
+        if changed {
+            glue_ref.set_changed();
+        }
+    }
+}
+
+/// Tints glued planks: pale while the glue is wet, turning amber as it cures.
+fn show_glue_state(
+    planks: Query<(&Glue, &ColorInfo, &Handle<ColorMaterial>), Changed<Glue>>,
+    mut materials: ResMut<Assets<ColorMaterial>>,
+) {
+    for (glue, color_info, material) in &planks {
+        let Some(material) = materials.get_mut(material) else {
+            continue;
+        };
+        let any_cured = glue.bonds.iter().any(|b| b.cured);
+        let glue_color = match glue.curing_progress() {
+            Some(progress) => GLUE_WET_COLOR.mix(&GLUE_CURED_COLOR, progress.min(1.)),
+            None if any_cured => GLUE_CURED_COLOR,
+            None => GLUE_WET_COLOR,
+        };
+        let amount = if any_cured && glue.curing_progress().is_none() { 0.2 } else { 0.35 };
+        material.color = color_info.0.mix(&glue_color, amount);
+    }
+}
@@ -0,0 +1,472 @@
+//! Undoing and redoing building actions.
+//!
+//! Every action that changes the build while building, like cutting, driving a fastener,
+//! drilling or deleting a plank, triggers a [`BuildAction`] before it takes effect, naming the
+//! planks it is about to change. The action keeps those planks as they were, so `Ctrl+Z` puts back
+//! just what it changed and removes the planks it made, and `Ctrl+Y` does it again. Grabbing
+//! planks triggers a [`GrabPlanks`] instead, which only counts as a move once they are let go of
+//! somewhere else.
+//!
+//! Planks an action removes are parked with [`ParkPlank`] instead of being despawned, so undoing
+//! brings them back as the same entities, and glue bonds, joints and everything else referring to
+//! them still do.
+
+use crate::demo::fastener::{Fastener, FastenerKind};
+use crate::demo::glue::Glue;
+use crate::demo::joint::{DrivenFastener, GlueLine, PlankJoint};
+use crate::demo::level::Plank;
+use crate::demo::save::{spawn_joint, PlankSave, Workshop};
+use crate::demo::score::BuildStats;
+use crate::demo::shop::Stock;
+use crate::demo::{BuildPhase, DeselectAll, OnPlankSpawned};
+use crate::screens::Screen;
+use avian2d::position::{Position, Rotation};
+use avian2d::prelude::{AngularVelocity, LinearVelocity};
+use bevy::ecs::entity::EntityHashSet;
+use bevy::ecs::system::SystemParam;
+use bevy::ecs::world::Command;
+use bevy::prelude::*;
+use bevy::utils::HashMap;
+use std::collections::BTreeMap;
+
+pub(super) fn plugin(app: &mut App) {
+    app.init_resource::<History>();
+    app.add_systems(OnEnter(Screen::Gameplay), clear_history);
+    app.add_systems(
+        Update,
+        (
+            undo.run_if(ctrl_just_pressed(KeyCode::KeyZ)),
+            redo.run_if(ctrl_just_pressed(KeyCode::KeyY)),
+        )
+            .run_if(in_state(BuildPhase::Building)),
+    );
+    app.add_systems(Last, finish_action);
+    app.observe(record_action);
+    app.observe(record_grab);
+    app.observe(record_move);
+    app.observe(record_spawned_plank);
+}
+
+/// Triggered by a building action before it changes anything, so it can be undone.
+///
+/// The planks are captured when the trigger runs, so the action has to make its changes with
+/// commands queued after it. Planks spawned in the same frame, like the pieces of a cut, are taken
+/// as the action's own.
+#[derive(Event)]
+pub struct BuildAction {
+    pub name: &'static str,
+    /// The planks the action changes or removes. Where the planks joined to them are and the glue
+    /// on them are kept too, as handling one plank moves whatever is built with it.
+    pub planks: Vec<Entity>,
+}
+
+/// Triggered when planks are grabbed, before they are moved. Letting go of them, i.e.
+/// [`DeselectAll`], records a move if they ended up somewhere else.
+#[derive(Event)]
+pub struct GrabPlanks(pub Vec<Entity>);
+
+impl BuildAction {
+    pub fn new(name: &'static str, planks: impl IntoIterator<Item = Entity>) -> Self {
+        let mut planks: Vec<Entity> = planks.into_iter().collect();
+        planks.sort();
+        planks.dedup();
+        Self { name, planks }
+    }
+}
+
+/// A [`Command`] that takes a plank out of the build but keeps its entity, so undoing can bring
+/// it back as the same plank. Parked planks go with the screen, like the rest of the level.
+pub struct ParkPlank(pub Entity);
+
+impl Command for ParkPlank {
+    fn apply(self, world: &mut World) {
+        if let Some(mut entity) = world.get_entity_mut(self.0) {
+            entity.retain::<StateScoped<Screen>>();
+        }
+    }
+}
+
+/// Where a plank is, how it is moving and the glue on it.
+struct PlankPose {
+    position: Position,
+    rotation: Rotation,
+    linear: LinearVelocity,
+    angular: AngularVelocity,
+    glue: Option<Glue>,
+}
+
+/// A joint holding one of an action's planks.
+struct JointState {
+    planks: [Entity; 2],
+    local_anchors: [Vec2; 2],
+    brace_anchors: Option<[Vec2; 2]>,
+    fasteners: Vec<(FastenerKind, DrivenFastener)>,
+    glue_lines: Vec<GlueLine>,
+}
+
+/// What an action changes, as it is at one point of the build.
+struct BuildState {
+    /// The action's planks that are part of the build at this point.
+    planks: HashMap<Entity, PlankSave>,
+    /// Joints holding any of the action's planks.
+    joints: Vec<JointState>,
+    /// The action's planks and every plank joined to them, directly or not.
+    poses: Vec<(Entity, PlankPose)>,
+    stats: BuildStats,
+    /// Fasteners left in stock, as driving one can use them up.
+    fasteners: BTreeMap<FastenerKind, u32>,
+}
+
+/// An action, with the build as it was on the other side of it.
+struct Action {
+    name: &'static str,
+    /// The planks the action changed, removed or made.
+    planks: Vec<Entity>,
+    /// The build before the action while it can be undone, after it while it can be redone.
+    state: BuildState,
+}
+
+impl Action {
+    /// Whether restoring the action could bring back `plank` or join anything to it.
+    fn refers_to(&self, plank: Entity) -> bool {
+        self.planks.contains(&plank)
+            || self.state.joints.iter().any(|joint| joint.planks.contains(&plank))
+    }
+}
+
+/// The planks and joints of the build, for capturing what an action changes.
+#[derive(SystemParam)]
+struct Build<'w, 's> {
+    workshop: Workshop<'w, 's>,
+    planks: Query<
+        'w,
+        's,
+        (
+            Entity,
+            &'static Position,
+            &'static Rotation,
+            Option<&'static LinearVelocity>,
+            Option<&'static AngularVelocity>,
+            Option<&'static Glue>,
+        ),
+        With<Plank>,
+    >,
+    joints: Query<'w, 's, (&'static PlankJoint, &'static Children)>,
+    fasteners: Query<'w, 's, (&'static Fastener, &'static DrivenFastener)>,
+    glue_lines: Query<'w, 's, &'static GlueLine>,
+    stats: Res<'w, BuildStats>,
+    stock: Res<'w, Stock>,
+}
+
+impl Build<'_, '_> {
+    /// `planks` and every plank joined to them, directly or through other planks.
+    fn connected(&self, planks: &[Entity]) -> EntityHashSet {
+        let mut connected: EntityHashSet = planks.iter().copied().collect();
+        let mut queue = planks.to_vec();
+        while let Some(plank) = queue.pop() {
+            for (joint, _) in &self.joints {
+                let other = match joint.planks {
+                    [a, b] if a == plank => b,
+                    [a, b] if b == plank => a,
+                    _ => continue,
+                };
+                if connected.insert(other) {
+                    queue.push(other);
+                }
+            }
+        }
+        connected
+    }
+
+    /// The build as it is now, as far as an action on `planks` changes it.
+    fn capture(&self, planks: &[Entity]) -> BuildState {
+        let moved = self.connected(planks);
+        BuildState {
+            planks: planks.iter().filter_map(|&e| Some((e, self.workshop.plank(e)?))).collect(),
+            joints: self
+                .joints
+                .iter()
+                .filter(|(joint, _)| joint.planks.iter().any(|p| planks.contains(p)))
+                .map(|(joint, children)| JointState {
+                    planks: joint.planks,
+                    local_anchors: joint.local_anchors,
+                    brace_anchors: joint.brace_anchors,
+                    fasteners: self
+                        .fasteners
+                        .iter_many(children)
+                        .map(|(fastener, driven)| (fastener.kind, driven.clone()))
+                        .collect(),
+                    glue_lines: self.glue_lines.iter_many(children).cloned().collect(),
+                })
+                .collect(),
+            poses: self
+                .planks
+                .iter_many(&moved)
+                .map(|(e, position, rotation, l_vel, a_vel, glue)| {
+                    let pose = PlankPose {
+                        position: *position,
+                        rotation: *rotation,
+                        linear: l_vel.copied().unwrap_or_default(),
+                        angular: a_vel.copied().unwrap_or_default(),
+                        glue: glue.cloned(),
+                    };
+                    (e, pose)
+                })
+                .collect(),
+            stats: self.stats.clone(),
+            fasteners: self.stock.fasteners.clone(),
+        }
+    }
+
+    /// Despawns the parked planks that only `action` could have brought back, i.e. those no
+    /// action left in `history` refers to.
+    fn forget(&self, action: Action, history: &History, commands: &mut Commands) {
+        for e in action.planks {
+            if self.planks.contains(e) || history.refers_to(e) {
+                continue;
+            }
+            if let Some(mut entity) = commands.get_entity(e) {
+                entity.despawn();
+            }
+        }
+    }
+
+    /// Whether any of the action's planks is no longer where `state` has it.
+    fn moved_since(&self, action: &Action) -> bool {
+        action.state.poses.iter().filter(|(e, _)| action.planks.contains(e)).any(|(e, pose)| {
+            self.planks.get(*e).map_or(true, |(_, position, rotation, ..)| {
+                position.distance(pose.position.0) > MIN_MOVE
+                    || (*rotation * Vec2::X).angle_between(pose.rotation * Vec2::X).abs() > MIN_TURN
+            })
+        })
+    }
+}
+
+impl Action {
+    /// Puts the build back as it is in the action's state, returning the action that would
+    /// reverse that.
+    fn restore(self, build: &Build, commands: &mut Commands) -> Action {
+        let reverse = Action {
+            name: self.name,
+            state: build.capture(&self.planks),
+            planks: self.planks.clone(),
+        };
+        // Let go of any plank being dragged, as it is about to be moved.
+        commands.trigger(DeselectAll);
+        commands.add(RestoreBuild {
+            planks: self.planks,
+            state: self.state,
+        });
+        reverse
+    }
+}
+
+/// A [`Command`] that puts an action's planks and their joints back as they are in a
+/// [`BuildState`], along with where the planks joined to them were.
+struct RestoreBuild {
+    planks: Vec<Entity>,
+    state: BuildState,
+}
+
+impl Command for RestoreBuild {
+    fn apply(self, world: &mut World) {
+        let RestoreBuild { planks, mut state } = self;
+        let joints: Vec<Entity> = world
+            .query::<(Entity, &PlankJoint)>()
+            .iter(world)
+            .filter(|(_, joint)| joint.planks.iter().any(|p| planks.contains(p)))
+            .map(|(e, _)| e)
+            .collect();
+        for e in joints {
+            world.entity_mut(e).despawn_recursive();
+        }
+
+        for e in planks {
+            if world.get_entity(e).is_none() {
+                warn!("Can't bring back {e:?}, which no longer exists");
+                continue;
+            }
+            match state.planks.remove(&e) {
+                Some(plank) => plank.spawn(world, e),
+                // The plank wasn't part of the build then.
+                None => ParkPlank(e).apply(world),
+            }
+        }
+
+        for joint in state.joints {
+            spawn_joint(
+                world,
+                PlankJoint {
+                    planks: joint.planks,
+                    local_anchors: joint.local_anchors,
+                    brace_anchors: joint.brace_anchors,
+                    compliance: 0.,
+                },
+                joint.fasteners,
+                joint.glue_lines,
+            );
+        }
+
+        for (e, pose) in state.poses {
+            // Planks that were parked since stay parked, unless the action brought them back.
+            let Some(mut entity) = world.get_entity_mut(e).filter(|entity| entity.contains::<Plank>()) else {
+                continue;
+            };
+            entity.insert((pose.position, pose.rotation, pose.linear, pose.angular));
+            match pose.glue {
+                Some(glue) => {
+                    entity.insert(glue);
+                }
+                None => {
+                    entity.remove::<Glue>();
+                }
+            }
+        }
+
+        let started = world.resource::<BuildStats>().started;
+        world.insert_resource(BuildStats { started, ..state.stats });
+        world.resource_mut::<Stock>().fasteners = state.fasteners;
+    }
+}
+
+/// Actions to go back and forth between, most recent last.
+#[derive(Resource, Default)]
+pub struct History {
+    undo: Vec<Action>,
+    redo: Vec<Action>,
+    /// Whether the last action is still being done, so planks spawned now are its own.
+    recording: bool,
+    /// The move of the planks being held, until they are let go of.
+    grab: Option<Action>,
+}
+
+impl History {
+    /// Adds an action to undo. Everything there was to redo is forgotten, and so is the oldest
+    /// action once there are more than [`MAX_UNDO`].
+    fn push(&mut self, action: Action, build: &Build, commands: &mut Commands) {
+        self.undo.push(action);
+        let mut forgotten = std::mem::take(&mut self.redo);
+        if self.undo.len() > MAX_UNDO {
+            forgotten.push(self.undo.remove(0));
+        }
+        for action in forgotten {
+            build.forget(action, self, commands);
+        }
+    }
+
+    /// Records the move of the planks being held if they were moved, before anything else
+    /// happens to them.
+    fn finish_grab(&mut self, build: &Build, commands: &mut Commands) {
+        if let Some(grab) = self.grab.take().filter(|grab| build.moved_since(grab)) {
+            self.push(grab, build, commands);
+        }
+    }
+
+    fn refers_to(&self, plank: Entity) -> bool {
+        self.undo.iter().chain(&self.redo).any(|action| action.refers_to(plank))
+    }
+}
+
+/// Older actions are forgotten, so a long build doesn't keep every plank it ever had.
+const MAX_UNDO: usize = 100;
+/// How far a grabbed plank has to be moved for the grab to count as a move, as planks sag a
+/// little just from being held.
+const MIN_MOVE: f32 = 1.;
+/// How far, in radians, a grabbed plank has to be turned for the grab to count as a move.
+const MIN_TURN: f32 = 0.02;
+
+fn ctrl_just_pressed(key: KeyCode) -> impl FnMut(Res<ButtonInput<KeyCode>>) -> bool + Clone {
+    move |input: Res<ButtonInput<KeyCode>>| {
+        input.any_pressed([KeyCode::ControlLeft, KeyCode::ControlRight]) && input.just_pressed(key)
+    }
+}
+
+fn clear_history(mut history: ResMut<History>) {
+    *history = default();
+}
+
+fn record_action(
+    trigger: Trigger<BuildAction>,
+    build: Build,
+    mut history: ResMut<History>,
+    mut commands: Commands,
+) {
+    let ev = trigger.event();
+    history.finish_grab(&build, &mut commands);
+    let action = Action {
+        name: ev.name,
+        planks: ev.planks.clone(),
+        state: build.capture(&ev.planks),
+    };
+    history.push(action, &build, &mut commands);
+    history.recording = true;
+}
+
+/// Keeps the build as it was when planks were grabbed, to record a move once they are let go of.
+fn record_grab(
+    trigger: Trigger<GrabPlanks>,
+    build: Build,
+    mut history: ResMut<History>,
+    mut commands: Commands,
+) {
+    history.finish_grab(&build, &mut commands);
+    let planks = &trigger.event().0;
+    history.grab = Some(Action {
+        name: "Move",
+        planks: planks.clone(),
+        state: build.capture(planks),
+    });
+}
+
+/// Records the move of the planks that were just let go of, if they were moved.
+fn record_move(
+    _trigger: Trigger<DeselectAll>,
+    build: Build,
+    mut history: ResMut<History>,
+    mut commands: Commands,
+) {
+    history.finish_grab(&build, &mut commands);
+}
+
+/// Takes planks spawned while an action is being done as the action's own, e.g. the pieces of
+/// a cut, so undoing the action removes them.
+fn record_spawned_plank(trigger: Trigger<OnPlankSpawned>, mut history: ResMut<History>) {
+    if !history.recording {
+        return;
+    }
+    if let Some(action) = history.undo.last_mut() {
+        action.planks.push(trigger.event().plank);
+    }
+}
+
+/// Actions are done by the end of the frame they were triggered in.
+fn finish_action(mut history: ResMut<History>) {
+    if history.recording {
+        history.recording = false;
+    }
+}
+
+fn undo(build: Build, mut history: ResMut<History>, mut commands: Commands) {
+    history.recording = false;
+    // Whatever is being held is let go of without counting as a move.
+    history.grab = None;
+    let Some(action) = history.undo.pop() else {
+        info!("Nothing to undo");
+        return;
+    };
+    info!("Undo {}", action.name);
+    let reverse = action.restore(&build, &mut commands);
+    history.redo.push(reverse);
+}
+
+fn redo(build: Build, mut history: ResMut<History>, mut commands: Commands) {
+    history.recording = false;
+    history.grab = None;
+    let Some(action) = history.redo.pop() else {
+        info!("Nothing to redo");
+        return;
+    };
+    info!("Redo {}", action.name);
+    let reverse = action.restore(&build, &mut commands);
+    history.undo.push(reverse);
+}
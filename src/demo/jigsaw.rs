@@ -0,0 +1,189 @@
+//! The jigsaw, which cuts planks along a freehand path drawn with the mouse.
+
+use crate::demo::history::BuildAction;
+use crate::demo::joint::Fastenings;
+use crate::demo::level::Plank;
+use crate::demo::lineage::PlankCut;
+use crate::demo::shape::PlankShape;
+use crate::demo::shop::Stock;
+use crate::demo::wood::{Grain, PlankSpecies};
+use crate::demo::{
+    get_world_pos, point_velocity, spawn_pieces, BuildPhase, ColorInfo, CutPlank, HeldTool,
+    SpawnPlank,
+};
+use crate::screens::Screen;
+use avian2d::position::{Position, Rotation};
+use avian2d::prelude::{AngularVelocity, LinearVelocity};
+use bevy::prelude::*;
+
+pub(super) fn plugin(app: &mut App) {
+    app.add_systems(
+        Update,
+        (toggle_jigsaw, trace_cut, draw_cut_path)
+            .chain()
+            .run_if(in_state(BuildPhase::Building)),
+    );
+}
+
+/// The jigsaw following the cursor while it is in hand.
+#[derive(Component, Default)]
+pub struct Jigsaw {
+    /// The path being cut, in world space. Empty while the blade isn't down.
+    path: Vec<Vec2>,
+}
+
+/// Minimum distance between two points of a cut path. Closer points make for jagged cuts.
+const PATH_STEP: f32 = 3.;
+const JIGSAW_SIZE: f32 = 4.;
+const JIGSAW_COLOR: Color = Color::srgb(0.2, 0.45, 0.75);
+const PATH_COLOR: Color = Color::srgb(0.9, 0.2, 0.2);
+
+fn toggle_jigsaw(
+    input: Res<ButtonInput<KeyCode>>,
+    jigsaws: Query<Entity, With<Jigsaw>>,
+    tools: Query<Entity, With<HeldTool>>,
+    mut commands: Commands,
+) {
+    if !input.just_pressed(KeyCode::KeyJ) {
+        return;
+    }
+    if jigsaws.is_empty() {
+        for tool in &tools {
+            commands.entity(tool).despawn();
+        }
+        commands.spawn((
+            Name::new("Jigsaw"),
+            Jigsaw::default(),
+            HeldTool,
+            SpriteBundle {
+                sprite: Sprite {
+                    color: JIGSAW_COLOR,
+                    custom_size: Some(Vec2::splat(JIGSAW_SIZE)),
+                    ..default()
+                },
+                transform: Transform::from_xyz(0., 200., 2.),
+                ..default()
+            },
+            StateScoped(Screen::Gameplay),
+        ));
+    } else {
+        for jigsaw in &jigsaws {
+            commands.entity(jigsaw).despawn();
+        }
+    }
+}
+
+/// Records the path while the mouse is held down, and cuts every plank it crossed on release.
+fn trace_cut(
+    mut jigsaw: Query<(&mut Jigsaw, &mut Transform)>,
+    planks: Query<
+        (
+            Entity,
+            &PlankShape,
+            &Position,
+            &Rotation,
+            Option<&LinearVelocity>,
+            Option<&AngularVelocity>,
+            &ColorInfo,
+            &PlankSpecies,
+            &Grain,
+        ),
+        With<Plank>,
+    >,
+    window: Query<&Window>,
+    camera: Query<(&Camera, &GlobalTransform)>,
+    mouse: Res<ButtonInput<MouseButton>>,
+    fastenings: Fastenings,
+    stock: Res<Stock>,
+    mut commands: Commands,
+) {
+    let Ok((mut jigsaw, mut transform)) = jigsaw.get_single_mut() else {
+        return;
+    };
+    let window = window.get_single().unwrap();
+    let (camera, gt) = camera.get_single().unwrap();
+    if let Some(cursor) = get_world_pos(window, camera, gt).map(|p| p.truncate()) {
+        transform.translation = cursor.extend(transform.translation.z);
+        if mouse.pressed(MouseButton::Left)
+            && jigsaw.path.last().map_or(true, |last| last.distance(cursor) >= PATH_STEP)
+        {
+            jigsaw.path.push(cursor);
+        }
+    }
+    if !mouse.just_released(MouseButton::Left) {
+        return;
+    }
+    let path = std::mem::take(&mut jigsaw.path);
+    if path.len() < 2 {
+        return;
+    }
+    // The cuts are worked out first, so one action covers every plank the path crossed.
+    let mut cuts = Vec::new();
+    for (e, shape, p, r, .., grain) in &planks {
+        let local_path: Vec<Vec2> = path.iter().map(|v| r.inverse() * (*v - p.0)).collect();
+        let Some(span) = shape.path_span(&local_path) else {
+            continue;
+        };
+        let kerf = Grain::kerf(stock.kerf(), grain.across_path(&local_path));
+        let pieces = shape.cut_along(&local_path, kerf);
+        if pieces.len() >= 2 {
+            cuts.push((e, local_path, span, pieces));
+        }
+    }
+    if cuts.is_empty() {
+        return;
+    }
+    commands.trigger(BuildAction::new("Cut", cuts.iter().map(|(e, ..)| *e)));
+    let mut cut_planks = Vec::new();
+    for (e, local_path, (entry, exit), pieces) in cuts {
+        let Ok((_, shape, p, r, l_vel, a_vel, color_info, species, grain)) = planks.get(e) else {
+            continue;
+        };
+        // Recorded from where the path enters the plank, towards where it leaves. A path that
+        // leaves where it came in goes the way it was drawn.
+        let direction = Some(exit - entry)
+            .filter(|d| *d != Vec2::ZERO)
+            .unwrap_or(local_path[local_path.len() - 1] - local_path[0]);
+        commands.trigger(PlankCut {
+            plank: e,
+            point: entry,
+            direction,
+            freehand: true,
+            sawdust: shape.sawdust(pieces.iter().map(|(piece, _)| piece)),
+        });
+        let linear = l_vel.map_or(Vec2::ZERO, |v| v.0);
+        let angular = a_vel.map_or(0., |v| v.0);
+        let pieces = pieces
+            .into_iter()
+            .map(|(piece, local_offset)| {
+                let offset = *r * local_offset;
+                SpawnPlank {
+                    shape: piece,
+                    position: p.0 + offset,
+                    rotation: *r,
+                    l_vel: Some(point_velocity(linear, angular, offset)),
+                    a_vel: Some(AngularVelocity(angular)),
+                    color: color_info.0,
+                    species: species.0.clone(),
+                    grain: *grain,
+                    joints: Vec::new(),
+                    origin: Some(e),
+                }
+            })
+            .collect();
+        cut_planks.push(CutPlank {
+            plank: e,
+            held: fastenings.of(e),
+            pieces,
+        });
+    }
+    spawn_pieces(&mut commands, cut_planks);
+}
+
+fn draw_cut_path(jigsaw: Query<&Jigsaw>, mut gizmos: Gizmos) {
+    for jigsaw in &jigsaw {
+        if jigsaw.path.len() >= 2 {
+            gizmos.linestrip_2d(jigsaw.path.iter().copied(), PATH_COLOR);
+        }
+    }
+}
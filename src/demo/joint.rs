@@ -0,0 +1,522 @@
+//! Joints that fasten planks together, and how much load they can take before breaking.
+
+use crate::asset_tracking::LoadResource;
+use crate::audio::SoundEffect;
+use crate::demo::fastener::{Fastener, FastenerHold, FastenerKind, FASTENER_HEAD};
+use crate::demo::level::Plank;
+use crate::demo::campaign::ActiveOrder;
+use crate::demo::score::BuildStats;
+use crate::demo::shop::Stock;
+use crate::demo::wood::{Grain, PlankSpecies, WoodSpecies};
+use crate::demo::shape::PlankShape;
+use crate::demo::{Fastening, ParentJoint};
+use crate::screens::Screen;
+use avian2d::position::{Position, Rotation};
+use avian2d::prelude::{Joint, RevoluteJoint};
+use bevy::ecs::query::QueryFilter;
+use bevy::ecs::system::{EntityCommands, SystemParam};
+use bevy::prelude::*;
+use bevy::sprite::Anchor;
+
+pub(super) fn plugin(app: &mut App) {
+    app.load_resource::<JointAssets>();
+    app.observe(drive_fastener);
+    app.observe(bond_glue);
+    app.observe(play_break_sound);
+    app.add_systems(
+        Update,
+        (remove_orphaned_joints, update_joints, follow_plank).run_if(in_state(Screen::Gameplay)),
+    );
+    app.add_systems(
+        FixedUpdate,
+        break_overloaded_joints.run_if(in_state(Screen::Gameplay)),
+    );
+}
+
+/// Marks the entity holding the physics joint between two planks.
+/// The fasteners making up the joint are its children.
+#[derive(Component)]
+pub struct PlankJoint {
+    pub planks: [Entity; 2],
+    /// Joint anchor in the local frame of each plank.
+    pub local_anchors: [Vec2; 2],
+    /// Anchors of the [`JointBrace`] in the local frame of each plank, if the joint is rigid.
+    pub brace_anchors: Option<[Vec2; 2]>,
+    /// Compliance of the physics joint, i.e. how much it gives under load.
+    pub compliance: f32,
+}
+
+/// A second pivot a short way from a [`PlankJoint`]'s anchor. Together the two pivots stop the
+/// planks from rotating relative to each other, at whatever angle they were joined.
+#[derive(Component)]
+pub struct JointBrace;
+
+/// How much load a [`PlankJoint`] can take before it breaks, and how much it is under.
+#[derive(Component, Debug, Default)]
+pub struct JointStrength {
+    /// Force at which the joint breaks.
+    pub limit: f32,
+    /// Smoothed force the joint is currently holding.
+    pub stress: f32,
+}
+
+/// Triggered when a joint takes more load than its [`JointStrength`] allows and comes apart.
+#[derive(Event)]
+pub struct JointBroken {
+    pub planks: [Entity; 2],
+    pub position: Vec2,
+}
+
+#[derive(Resource, Asset, Reflect, Clone)]
+pub struct JointAssets {
+    #[dependency]
+    snap: Handle<AudioSource>,
+}
+
+impl JointAssets {
+    pub const PATH_SNAP: &'static str = "audio/sound_effects/snap.wav";
+}
+
+impl FromWorld for JointAssets {
+    fn from_world(world: &mut World) -> Self {
+        let assets = world.resource::<AssetServer>();
+        Self {
+            snap: assets.load(Self::PATH_SNAP),
+        }
+    }
+}
+
+/// Glue is stiffer than any fastener, but gives way at a smaller deformation.
+const GLUE_COMPLIANCE: f32 = 2e-8;
+/// Force held per unit length of cured glue line.
+const GLUE_STRENGTH: f32 = 5e4;
+/// Distance between a rigid joint's anchor and its [`JointBrace`].
+const BRACE_SPAN: f32 = 5.;
+/// How quickly [`JointStrength::stress`] follows the measured force, to ignore single-step spikes.
+const STRESS_SMOOTHING: f32 = 0.3;
+/// Overlap between two planks that gives fasteners their nominal strength.
+const REFERENCE_OVERLAP: f32 = 100.;
+
+/// A fastener that has been driven into a plank. Follows the plank it was driven into.
+#[derive(Component, Clone)]
+pub struct DrivenFastener {
+    pub plank: Entity,
+    /// Fastener position in the plank's local frame.
+    pub local_position: Vec2,
+    /// Fastener angle relative to the plank, in radians.
+    pub local_angle: f32,
+}
+
+/// Drives a fastener at `position` through all the given planks, joining them together.
+#[derive(Event)]
+pub struct DriveFastener {
+    pub kind: FastenerKind,
+    pub planks: Vec<Entity>,
+    pub position: Vec2,
+    pub rotation: Rotation,
+}
+
+/// A line of glue that has cured between two planks.
+#[derive(Component, Clone)]
+pub struct GlueLine {
+    pub length: f32,
+    /// Middle of the line in the local frame of each of the joint's planks, in the same order as
+    /// [`PlankJoint::planks`].
+    pub local_anchors: [Vec2; 2],
+}
+
+pub fn glue_line_bundle(glue_line: GlueLine) -> impl Bundle {
+    (Name::new("Glue line"), glue_line)
+}
+
+/// Bonds two planks with cured glue along `length` units of edge around `position`.
+#[derive(Event)]
+pub struct BondGlue {
+    pub planks: [Entity; 2],
+    pub position: Vec2,
+    pub length: f32,
+}
+
+/// Finds the joint between two planks, or spawns a new one anchored at `position`.
+fn find_or_spawn_joint<'a>(
+    commands: &'a mut Commands,
+    joints: &Query<(Entity, &PlankJoint)>,
+    planks: [(Entity, &Position, &Rotation); 2],
+    position: Vec2,
+) -> EntityCommands<'a> {
+    let [(first, first_pos, first_rot), (other, other_pos, other_rot)] = planks;
+    let existing = joints
+        .iter()
+        .find(|(_, joint)| joint.planks == [first, other] || joint.planks == [other, first]);
+    match existing {
+        Some((e, _)) => commands.entity(e),
+        None => commands.spawn(joint_bundle(PlankJoint {
+            planks: [first, other],
+            local_anchors: [
+                first_rot.inverse() * (position - first_pos.0),
+                other_rot.inverse() * (position - other_pos.0),
+            ],
+            brace_anchors: None,
+            compliance: 0.,
+        })),
+    }
+}
+
+/// Components of a joint entity. Its physics joint is built once fasteners or glue are added
+/// as children.
+pub fn joint_bundle(joint: PlankJoint) -> impl Bundle {
+    (
+        Name::new("Plank joint"),
+        joint,
+        JointStrength::default(),
+        SpatialBundle::default(),
+        StateScoped(Screen::Gameplay),
+    )
+}
+
+/// Components of a fastener driven into a plank, to be spawned as a child of its joint.
+pub fn driven_fastener_bundle(kind: FastenerKind, transform: Transform, driven: DrivenFastener) -> impl Bundle {
+    let spec = kind.spec();
+    (
+        Name::new(spec.name),
+        Fastener { kind },
+        SpriteBundle {
+            sprite: Sprite {
+                color: spec.color,
+                custom_size: Some(Vec2::new(spec.width, spec.length)),
+                anchor: Anchor::Custom(Vec2::new(0., 0.5 - FASTENER_HEAD)),
+                ..default()
+            },
+            transform,
+            ..default()
+        },
+        driven,
+    )
+}
+
+/// Whether a fastener of `kind` can be driven through `planks` at `position`. Those that need a
+/// hole need one in every plank.
+pub fn fastener_fits<F: QueryFilter>(
+    kind: FastenerKind,
+    planks: &[Entity],
+    position: Vec2,
+    shapes: &Query<(&PlankShape, &Position, &Rotation), F>,
+) -> bool {
+    !kind.spec().needs_hole
+        || planks.iter().all(|p| {
+            shapes
+                .get(*p)
+                .is_ok_and(|(shape, pos, rot)| shape.has_hole_at(rot.inverse() * (position - pos.0)))
+        })
+}
+
+fn drive_fastener(
+    trigger: Trigger<DriveFastener>,
+    planks: Query<(&PlankShape, &Position, &Rotation), With<Plank>>,
+    joints: Query<(Entity, &PlankJoint)>,
+    mut stats: ResMut<BuildStats>,
+    mut stock: ResMut<Stock>,
+    order: Option<Res<ActiveOrder>>,
+    mut commands: Commands,
+) {
+    let ev = trigger.event();
+    let spec = ev.kind.spec();
+    if !fastener_fits(ev.kind, &ev.planks, ev.position, &planks) {
+        info!("A {} needs a pre-drilled hole in every plank", spec.name);
+        return;
+    }
+    let Some((&first, rest)) = ev.planks.split_first() else {
+        return;
+    };
+    let Ok((_, first_pos, first_rot)) = planks.get(first) else {
+        return;
+    };
+    stats.fasteners += 1;
+    // Fasteners bought at the lumber yard are used up before paying out of an order's budget.
+    if order.is_none() || !stock.take_fastener(ev.kind) {
+        stats.spent += spec.cost;
+    }
+    for &other in rest {
+        let Ok((_, other_pos, other_rot)) = planks.get(other) else {
+            continue;
+        };
+        find_or_spawn_joint(
+            &mut commands,
+            &joints,
+            [(first, first_pos, first_rot), (other, other_pos, other_rot)],
+            ev.position,
+        )
+        .with_children(|children| {
+            children.spawn(driven_fastener_bundle(
+                ev.kind,
+                Transform::from_translation(ev.position.extend(1.))
+                    .with_rotation(Quat::from_rotation_z(ev.rotation.as_radians())),
+                DrivenFastener {
+                    plank: first,
+                    local_position: first_rot.inverse() * (ev.position - first_pos.0),
+                    local_angle: ev.rotation.as_radians() - first_rot.as_radians(),
+                },
+            ));
+        });
+    }
+}
+
+fn bond_glue(
+    trigger: Trigger<BondGlue>,
+    planks: Query<(&Position, &Rotation), With<Plank>>,
+    joints: Query<(Entity, &PlankJoint)>,
+    mut commands: Commands,
+) {
+    let ev = trigger.event();
+    let [first, other] = ev.planks;
+    let Ok([(first_pos, first_rot), (other_pos, other_rot)]) = planks.get_many(ev.planks) else {
+        return;
+    };
+    let mut local_anchors = [
+        first_rot.inverse() * (ev.position - first_pos.0),
+        other_rot.inverse() * (ev.position - other_pos.0),
+    ];
+    // An existing joint may list the planks the other way round.
+    if joints.iter().any(|(_, joint)| joint.planks == [other, first]) {
+        local_anchors.reverse();
+    }
+    find_or_spawn_joint(
+        &mut commands,
+        &joints,
+        [(first, first_pos, first_rot), (other, other_pos, other_rot)],
+        ev.position,
+    )
+    .with_children(|children| {
+        children.spawn(glue_line_bundle(GlueLine {
+            length: ev.length,
+            local_anchors,
+        }));
+    });
+}
+
+/// The fasteners and glue lines in every joint, for finding out what holds a plank.
+#[derive(SystemParam)]
+pub struct Fastenings<'w, 's> {
+    joints: Query<'w, 's, (&'static PlankJoint, &'static Children)>,
+    fasteners: Query<'w, 's, (&'static Fastener, &'static DrivenFastener)>,
+    glue_lines: Query<'w, 's, &'static GlueLine>,
+    planks: Query<'w, 's, (&'static Position, &'static Rotation), With<Plank>>,
+}
+
+impl Fastenings<'_, '_> {
+    /// The fasteners and cured glue lines joining `plank` to other planks, in world space, each
+    /// with the plank it joins to as the parent. Pieces cut from `plank` can keep the ones that
+    /// land in them.
+    pub fn of(&self, plank: Entity) -> Vec<ParentJoint> {
+        let Ok((plank_pos, plank_rot)) = self.planks.get(plank) else {
+            return Vec::new();
+        };
+        let mut fastenings = Vec::new();
+        for (joint, children) in &self.joints {
+            let (side, parent) = match joint.planks {
+                [a, b] if a == plank => (0, b),
+                [a, b] if b == plank => (1, a),
+                _ => continue,
+            };
+            for (fastener, driven) in self.fasteners.iter_many(children) {
+                let Ok((position, rotation)) = self.planks.get(driven.plank) else {
+                    continue;
+                };
+                fastenings.push(ParentJoint {
+                    parent,
+                    position: position.0 + *rotation * driven.local_position,
+                    fastening: Fastening::Fastener {
+                        kind: fastener.kind,
+                        rotation: rotation.add_angle(driven.local_angle),
+                    },
+                });
+            }
+            for glue_line in self.glue_lines.iter_many(children) {
+                fastenings.push(ParentJoint {
+                    parent,
+                    position: plank_pos.0 + *plank_rot * glue_line.local_anchors[side],
+                    fastening: Fastening::Glue {
+                        length: glue_line.length,
+                    },
+                });
+            }
+        }
+        fastenings
+    }
+}
+
+/// Despawns joints whose planks no longer exist, e.g. because they were cut.
+fn remove_orphaned_joints(
+    joints: Query<(Entity, &PlankJoint)>,
+    planks: Query<(), With<Plank>>,
+    mut commands: Commands,
+) {
+    for (e, joint) in &joints {
+        if joint.planks.iter().any(|p| !planks.contains(*p)) {
+            commands.entity(e).despawn_recursive();
+        }
+    }
+}
+
+/// Rebuilds the physics joint of every [`PlankJoint`] whose fasteners changed.
+///
+/// The fasteners decide whether the planks can still pivot, how stiff the joint is and how much
+/// it holds. More fasteners and more overlapping wood make for a stronger joint, and so does
+/// harder wood: the softer of the two species decides. Fasteners driven along the grain of
+/// either plank go into end grain and hold less.
+fn update_joints(
+    mut joints: Query<(Entity, &mut PlankJoint, &Children, &mut JointStrength), Changed<Children>>,
+    fasteners: Query<(&Fastener, &DrivenFastener)>,
+    glue_lines: Query<&GlueLine>,
+    braces: Query<Entity, With<JointBrace>>,
+    planks: Query<(&PlankShape, &Position, &Rotation, &Grain), With<Plank>>,
+    plank_species: Query<&PlankSpecies>,
+    species: Res<Assets<WoodSpecies>>,
+    mut commands: Commands,
+) {
+    for (e, mut joint, children, mut strength) in &mut joints {
+        let Ok([(shape_1, pos_1, rot_1, grain_1), (shape_2, pos_2, rot_2, grain_2)]) =
+            planks.get_many(joint.planks)
+        else {
+            continue;
+        };
+        let overlap = shape_1.overlap_area(pos_1.0, *rot_1, shape_2, pos_2.0, *rot_2);
+        let overlap_factor = (overlap / REFERENCE_OVERLAP).clamp(0.25, 2.);
+
+        let specs: Vec<_> = fasteners.iter_many(children).map(|(f, _)| f.kind.spec()).collect();
+        let fastener_strength: f32 = fasteners
+            .iter_many(children)
+            .map(|(fastener, driven)| {
+                let Ok((.., driven_rot, _)) = planks.get(driven.plank) else {
+                    return fastener.kind.spec().strength;
+                };
+                let direction = driven_rot.add_angle(driven.local_angle) * Vec2::Y;
+                let hold = grain_1
+                    .fastener_hold(rot_1.inverse() * direction)
+                    .min(grain_2.fastener_hold(rot_2.inverse() * direction));
+                fastener.kind.spec().strength * hold
+            })
+            .sum();
+        let glue_length: f32 = glue_lines.iter_many(children).map(|g| g.length).sum();
+        let pins = specs.iter().filter(|s| s.hold == FastenerHold::Pin).count();
+        let rigid = glue_length > 0.
+            || pins >= 2
+            || specs.iter().any(|s| s.hold == FastenerHold::Rigid);
+        let fastener_compliance = specs.iter().map(|s| s.compliance).reduce(f32::min);
+        let compliance = match (fastener_compliance, glue_length > 0.) {
+            (Some(c), true) => c.min(GLUE_COMPLIANCE),
+            (Some(c), false) => c,
+            (None, true) => GLUE_COMPLIANCE,
+            (None, false) => {
+                // Nothing holds the planks together any more.
+                commands.entity(e).despawn_recursive();
+                continue;
+            }
+        };
+        let wood_factor = plank_species
+            .iter_many(joint.planks)
+            .map(|s| species.get(&s.0).map_or(1., |s| s.joint_strength))
+            .fold(f32::INFINITY, f32::min);
+        let wood_factor = if wood_factor.is_finite() { wood_factor } else { 1. };
+        strength.limit = (fastener_strength * overlap_factor
+            + GLUE_STRENGTH * glue_length)
+            * wood_factor;
+        joint.compliance = compliance;
+
+        let [plank_1, plank_2] = joint.planks;
+        commands.entity(e).insert(
+            RevoluteJoint::new(plank_1, plank_2)
+                .with_local_anchor_1(joint.local_anchors[0])
+                .with_local_anchor_2(joint.local_anchors[1])
+                .with_compliance(compliance),
+        );
+        if !rigid {
+            continue;
+        }
+        let local_anchor = joint.local_anchors[0];
+        let brace_anchors = *joint.brace_anchors.get_or_insert_with(|| {
+            let brace = pos_1.0 + *rot_1 * (local_anchor + Vec2::X * BRACE_SPAN);
+            [rot_1.inverse() * (brace - pos_1.0), rot_2.inverse() * (brace - pos_2.0)]
+        });
+        let brace_joint = RevoluteJoint::new(plank_1, plank_2)
+            .with_local_anchor_1(brace_anchors[0])
+            .with_local_anchor_2(brace_anchors[1])
+            .with_compliance(compliance);
+        match braces.iter_many(children).next() {
+            Some(brace) => {
+                commands.entity(brace).insert(brace_joint);
+            }
+            None => {
+                commands.entity(e).with_children(|children| {
+                    children.spawn((Name::new("Joint brace"), JointBrace, brace_joint));
+                });
+            }
+        }
+    }
+}
+
+/// Estimates the force each joint carries from its constraint error and breaks the joints that
+/// carry more than they can hold.
+fn break_overloaded_joints(
+    mut joints: Query<(Entity, &PlankJoint, &mut JointStrength)>,
+    planks: Query<(&Position, &Rotation), With<Plank>>,
+    mut commands: Commands,
+) {
+    for (e, joint, mut strength) in &mut joints {
+        // Joints start out with no compliance until their fasteners have been counted.
+        if joint.compliance <= 0. {
+            continue;
+        }
+        let Ok([(pos_1, rot_1), (pos_2, rot_2)]) = planks.get_many(joint.planks) else {
+            continue;
+        };
+        let anchor_error = |anchors: [Vec2; 2]| {
+            let world_1 = pos_1.0 + *rot_1 * anchors[0];
+            let world_2 = pos_2.0 + *rot_2 * anchors[1];
+            (world_1.distance(world_2), (world_1 + world_2) / 2.)
+        };
+        let (mut error, position) = anchor_error(joint.local_anchors);
+        if let Some(brace_anchors) = joint.brace_anchors {
+            error += anchor_error(brace_anchors).0;
+        }
+        // Soft constraints give way in proportion to the load they carry.
+        let force = error / joint.compliance;
+        strength.stress += (force - strength.stress) * STRESS_SMOOTHING;
+        if strength.limit > 0. && strength.stress > strength.limit {
+            info!("Joint broke under {:.0} (limit {:.0})", strength.stress, strength.limit);
+            commands.entity(e).despawn_recursive();
+            commands.trigger(JointBroken {
+                planks: joint.planks,
+                position,
+            });
+        }
+    }
+}
+
+fn play_break_sound(_trigger: Trigger<JointBroken>, assets: Option<Res<JointAssets>>, mut commands: Commands) {
+    let Some(assets) = assets else {
+        return;
+    };
+    commands.spawn((
+        AudioBundle {
+            source: assets.snap.clone(),
+            settings: PlaybackSettings::DESPAWN,
+        },
+        SoundEffect,
+    ));
+}
+
+/// Keeps driven fasteners on their plank. Joint entities sit at the origin, so the fastener's
+/// local transform is also its world transform.
+fn follow_plank(
+    mut fasteners: Query<(&DrivenFastener, &mut Transform)>,
+    planks: Query<(&Position, &Rotation), With<Plank>>,
+) {
+    for (fastener, mut transform) in &mut fasteners {
+        if let Ok((position, rotation)) = planks.get(fastener.plank) {
+            let world = position.0 + *rotation * fastener.local_position;
+            transform.translation = world.extend(transform.translation.z);
+            transform.rotation = Quat::from_rotation_z(rotation.as_radians() + fastener.local_angle);
+        }
+    }
+}
@@ -1,16 +1,38 @@
 //! Spawn the main level.
+//!
+//! Levels are described by `.level.ron` files in `assets/levels`, loaded as [`LevelDef`] assets.
 
-use rand::{Rng, thread_rng};
+use rand::Rng;
+use crate::asset_tracking::LoadResource;
 use crate::demo::{SAW_HEIGHT, Selectable, SpawnPlank};
+use crate::demo::blueprint::Blueprint;
+use crate::demo::campaign::ActiveOrder;
+use crate::demo::fastener::FastenerKind;
+use crate::demo::save::PendingRestore;
+use crate::demo::seed::{GameRng, LevelSeed};
+use crate::demo::shape::PlankShape;
+use crate::demo::shop::Stock;
+use crate::demo::wood::{Grain, WoodAssets, WoodSpecies};
+use crate::theme::prelude::*;
 use avian2d::collision::Sensor;
-use avian2d::prelude::{Collider, MassPropertiesBundle, RigidBody, Rotation};
-use bevy::{ecs::world::Command, prelude::*};
+use avian2d::prelude::{Collider, ColliderDensity, MassPropertiesBundle, RigidBody, Rotation};
+use bevy::asset::io::Reader;
+use bevy::asset::{AssetLoader, AsyncReadExt, LoadContext};
+use bevy::ui::Val::Px;
+use bevy::prelude::*;
+use bevy::sprite::Mesh2dHandle;
+use serde::{Deserialize, Serialize};
+use std::f32::consts::PI;
 use crate::screens::Screen;
 
-pub(super) fn plugin(_app: &mut App) {
-    // No setup required for this plugin.
-    // It's still good to have a function here so that we can add some setup
-    // later if needed.
+pub(super) fn plugin(app: &mut App) {
+    app.init_asset::<LevelDef>();
+    app.register_asset_loader(LevelLoader);
+    app.load_resource::<LevelAssets>();
+    app.add_systems(Update, init_current_level.run_if(resource_added::<LevelAssets>));
+    app.add_systems(Update, draw_prop_parts);
+    #[cfg(feature = "dev_native")]
+    app.add_systems(Update, respawn_modified_level.run_if(in_state(Screen::Gameplay)));
 }
 
 #[derive(Component)]
@@ -19,85 +41,529 @@ pub struct Plank;
 #[derive(Component)]
 pub struct Saw {
     pub active: bool,
+    /// Blade tilt in degrees, clockwise from vertical. Cuts follow this angle.
+    pub angle: f32,
 }
 
+/// Text on the saw blade showing its current [`Saw::angle`].
+#[derive(Component)]
+pub struct SawAngleLabel;
+
 #[derive(Component)]
 pub struct SawBody;
 
-/// A [`Command`] to spawn the level.
-/// Functions that accept only `&mut World` as their parameter implement [`Command`].
-/// We use this style when a command requires no configuration.
+/// Marks everything spawned from a [`LevelDef`] apart from planks, so the level can be respawned.
+#[derive(Component)]
+pub struct LevelEntity;
+
+/// A prop spawned from a level, along with its definition.
+#[derive(Component)]
+pub struct Prop(pub PropDef);
+
+/// A level layout: the ground, the planks to work with, props, tools and goals.
+#[derive(Asset, TypePath, Serialize, Deserialize, Clone, Debug)]
+pub struct LevelDef {
+    #[serde(default = "default_ground")]
+    pub ground: bool,
+    /// Planks placed exactly where they are listed.
+    #[serde(default)]
+    pub planks: Vec<PlankDef>,
+    /// A randomly stacked pile of planks, dealt from the [`LevelSeed`].
+    #[serde(default)]
+    pub pile: Option<PileDef>,
+    #[serde(default)]
+    pub props: Vec<PropDef>,
+    /// The table saw. Levels without one can only be worked with hand tools.
+    #[serde(default)]
+    pub saw: Option<SawDef>,
+    /// Fastener kinds offered in the toolbar.
+    #[serde(default = "default_fasteners")]
+    pub fasteners: Vec<FastenerKind>,
+    /// What the player is asked to build, shown at the top left of the screen.
+    #[serde(default)]
+    pub goals: Vec<String>,
+    /// Path of the [`Blueprint`] the build is checked against, relative to the assets folder.
+    #[serde(default)]
+    pub blueprint: Option<String>,
+    #[serde(skip)]
+    pub blueprint_handle: Handle<Blueprint>,
+    /// The weights dropped on the build when it is load tested.
+    #[serde(default)]
+    pub load_test: Option<LoadTestDef>,
+    /// The customer's order, if the level is part of the campaign.
+    #[serde(default)]
+    pub order: Option<OrderDef>,
+}
+
+/// What a customer asks for. The level's planks are the stock the order is built from.
+#[derive(Serialize, Deserialize, Clone, Debug)]
+pub struct OrderDef {
+    pub customer: String,
+    /// The order in the customer's words.
+    pub request: String,
+    /// How much may be spent on fasteners.
+    pub budget: u32,
+    /// What the customer pays the first time the order is fulfilled.
+    pub payment: u32,
+    /// How tall the build has to be, at least.
+    #[serde(default)]
+    pub min_height: Option<f32>,
+    /// Whether the build has to pass the load test.
+    #[serde(default)]
+    pub load_bearing: bool,
+}
+
+#[derive(Serialize, Deserialize, Clone, Debug)]
+pub struct LoadTestDef {
+    /// How long the build has to hold up, in seconds.
+    pub duration: f32,
+    /// Weights dropped on the build. Their positions are relative to the top centre of the build.
+    pub weights: Vec<PropDef>,
+}
+
+fn default_ground() -> bool {
+    true
+}
+
+fn default_fasteners() -> Vec<FastenerKind> {
+    FastenerKind::ALL.to_vec()
+}
+
+#[derive(Serialize, Deserialize, Clone, Debug)]
+pub struct PlankDef {
+    pub position: Vec2,
+    pub size: Vec2,
+    /// Rotation in degrees, counterclockwise.
+    #[serde(default)]
+    pub rotation: f32,
+    /// sRGB colour. A random colour of the species is used if this is left out.
+    #[serde(default)]
+    pub color: Option<[f32; 3]>,
+    /// Path of the [`WoodSpecies`], relative to the assets folder. Defaults to
+    /// [`WoodAssets::PATH_DEFAULT`].
+    #[serde(default)]
+    pub species: Option<String>,
+    #[serde(skip)]
+    pub species_handle: Handle<WoodSpecies>,
+    /// Direction of the grain in degrees, counterclockwise from the plank's length.
+    #[serde(default)]
+    pub grain: f32,
+}
+
+/// A stack of planks with slightly random positions, lengths and tilts.
+#[derive(Serialize, Deserialize, Clone, Debug)]
+pub struct PileDef {
+    pub count: u32,
+    /// Centre of the bottom plank.
+    pub position: Vec2,
+    /// Size of the planks in the pile. Their lengths vary by a few units either way.
+    pub size: Vec2,
+    /// Path of the [`WoodSpecies`], relative to the assets folder. Defaults to
+    /// [`WoodAssets::PATH_DEFAULT`].
+    #[serde(default)]
+    pub species: Option<String>,
+    #[serde(skip)]
+    pub species_handle: Handle<WoodSpecies>,
+}
+
+/// A dynamic object with a compound collider. It is drawn with its image, or with the shapes
+/// of its colliders if it has none.
+#[derive(Serialize, Deserialize, Clone, Debug)]
+pub struct PropDef {
+    pub name: String,
+    /// Path of the image, relative to the assets folder.
+    #[serde(default)]
+    pub image: Option<String>,
+    /// sRGB colour of the collider shapes, if there is no image.
+    #[serde(default)]
+    pub color: Option<[f32; 3]>,
+    pub position: Vec2,
+    /// Rotation in degrees, counterclockwise.
+    #[serde(default)]
+    pub rotation: f32,
+    #[serde(default = "default_scale")]
+    pub scale: f32,
+    pub mass: f32,
+    /// Collider parts, in image pixels if there is an image, before scaling.
+    pub colliders: Vec<ColliderDef>,
+    #[serde(default)]
+    pub selectable: bool,
+    #[serde(skip)]
+    pub image_handle: Handle<Image>,
+}
+
+fn default_scale() -> f32 {
+    1.
+}
+
+#[derive(Serialize, Deserialize, Clone, Debug)]
+pub struct ColliderDef {
+    #[serde(default)]
+    pub offset: Vec2,
+    /// Rotation in degrees, counterclockwise.
+    #[serde(default)]
+    pub rotation: f32,
+    pub shape: ShapeDef,
+}
+
+#[derive(Serialize, Deserialize, Clone, Copy, Debug)]
+pub enum ShapeDef {
+    Circle { radius: f32 },
+    Rectangle { width: f32, height: f32 },
+    /// A capsule standing upright, `length` being the length of its straight section.
+    Capsule { radius: f32, length: f32 },
+}
+
+impl ShapeDef {
+    fn collider(self) -> Collider {
+        match self {
+            ShapeDef::Circle { radius } => Collider::circle(radius),
+            ShapeDef::Rectangle { width, height } => Collider::rectangle(width, height),
+            ShapeDef::Capsule { radius, length } => Collider::capsule(radius, length),
+        }
+    }
+
+    fn mesh(self) -> Mesh {
+        match self {
+            ShapeDef::Circle { radius } => Circle::new(radius).into(),
+            ShapeDef::Rectangle { width, height } => Rectangle::new(width, height).into(),
+            ShapeDef::Capsule { radius, length } => Capsule2d::new(radius, length).into(),
+        }
+    }
+}
+
+#[derive(Serialize, Deserialize, Clone, Debug)]
+pub struct SawDef {
+    /// Horizontal position of the blade. The saw always sits just above the ground.
+    #[serde(default)]
+    pub x: f32,
+}
+
+/// Loads [`LevelDef`]s from RON files, along with the images of their props and the blueprint.
+#[derive(Default)]
+pub struct LevelLoader;
+
+#[derive(Debug, thiserror::Error)]
+pub enum LevelLoaderError {
+    #[error("could not read level file: {0}")]
+    Io(#[from] std::io::Error),
+    #[error("could not parse level file: {0}")]
+    Ron(#[from] ron::error::SpannedError),
+}
+
+impl AssetLoader for LevelLoader {
+    type Asset = LevelDef;
+    type Settings = ();
+    type Error = LevelLoaderError;
+
+    async fn load<'a>(
+        &'a self,
+        reader: &'a mut Reader<'_>,
+        _settings: &'a (),
+        load_context: &'a mut LoadContext<'_>,
+    ) -> Result<LevelDef, LevelLoaderError> {
+        let mut bytes = Vec::new();
+        reader.read_to_end(&mut bytes).await?;
+        let mut level: LevelDef = ron::de::from_bytes(&bytes)?;
+        let weights = level.load_test.iter_mut().flat_map(|t| &mut t.weights);
+        for prop in level.props.iter_mut().chain(weights) {
+            if let Some(image) = &prop.image {
+                prop.image_handle = load_context.load(image.clone());
+            }
+        }
+        if let Some(blueprint) = &level.blueprint {
+            level.blueprint_handle = load_context.load(blueprint.clone());
+        }
+        for plank in &mut level.planks {
+            let path = plank.species.as_deref().unwrap_or(WoodAssets::PATH_DEFAULT);
+            plank.species_handle = load_context.load(path.to_string());
+        }
+        if let Some(pile) = &mut level.pile {
+            let path = pile.species.as_deref().unwrap_or(WoodAssets::PATH_DEFAULT);
+            pile.species_handle = load_context.load(path.to_string());
+        }
+        Ok(level)
+    }
+
+    fn extensions(&self) -> &[&str] {
+        &["level.ron"]
+    }
+}
+
+#[derive(Resource, Asset, Reflect, Clone)]
+pub struct LevelAssets {
+    #[dependency]
+    pub workshop: Handle<LevelDef>,
+}
+
+impl LevelAssets {
+    pub const PATH_WORKSHOP: &'static str = "levels/workshop.level.ron";
+}
+
+impl FromWorld for LevelAssets {
+    fn from_world(world: &mut World) -> Self {
+        let assets = world.resource::<AssetServer>();
+        Self {
+            workshop: assets.load(Self::PATH_WORKSHOP),
+        }
+    }
+}
+
+/// The level to spawn when entering gameplay. Starts out as [`LevelAssets::workshop`].
+#[derive(Resource)]
+pub struct CurrentLevel(pub Handle<LevelDef>);
+
+fn init_current_level(mut commands: Commands, level_assets: Res<LevelAssets>) {
+    commands.insert_resource(CurrentLevel(level_assets.workshop.clone()));
+}
+
+/// A [`Command`](bevy::ecs::world::Command) to spawn the [`CurrentLevel`].
+///
+/// The plank pile is dealt from [`LevelSeed`], so the same seed always gives the same level.
+/// Planks bought at the lumber yard are delivered to the workshop of an order in place of the
+/// pile, and stay in stock in free play. When a saved workshop is about to be restored they stay
+/// in stock too, as the restore would replace them.
 pub fn spawn_level(world: &mut World) {
+    let handle = world.resource::<CurrentLevel>().0.clone();
+    let Some(level) = world.resource::<Assets<LevelDef>>().get(&handle).cloned() else {
+        error!("Level {:?} is not loaded", handle.path());
+        return;
+    };
+    // The level belongs to whichever screen spawned it, gameplay or the editor.
+    let screen = world.resource::<State<Screen>>().get().clone();
+    let mut level = level;
+    if screen == Screen::Gameplay
+        && world.contains_resource::<ActiveOrder>()
+        && !world.contains_resource::<PendingRestore>()
+    {
+        let asset_server = world.resource::<AssetServer>().clone();
+        let delivered = world.resource_mut::<Stock>().deliver_planks(&asset_server);
+        if !delivered.is_empty() {
+            level.pile = None;
+            level.planks.extend(delivered);
+        }
+    }
+    let seed = *world.resource::<LevelSeed>();
+    world.insert_resource(GameRng::new(seed.0));
+    world.resource_scope(|world, mut rng: Mut<GameRng>| {
+        world.resource_scope(|world, species: Mut<Assets<WoodSpecies>>| {
+            spawn_level_def(world, &level, screen, &mut rng, &species)
+        })
+    });
+}
+
+fn spawn_level_def(
+    world: &mut World,
+    level: &LevelDef,
+    screen: Screen,
+    rng: &mut GameRng,
+    species: &Assets<WoodSpecies>,
+) {
+    let rng = &mut rng.0;
+    let default_species = WoodSpecies::default();
+    let wood = |handle: &Handle<WoodSpecies>| species.get(handle).unwrap_or(&default_species);
     let mut commands = world.commands();
-    commands.spawn((
-        Transform::default(),
-        RigidBody::Static,
-        Collider::segment(Vec2::new(-100000., 0.), Vec2::new(100000., 0.)),
-        StateScoped(Screen::Gameplay)
-    ));
-    let mut rng = thread_rng();
-    for i in 0..10 {
-        let pos_y = 5. + (i as f32) * 10. + rng.gen_range(1.5..3.5);
-        let pos_x = -200. + rng.gen_range(-20f32..20.);
-        let width = 200. + rng.gen_range(-25f32..5.);
-        let rotation = Rotation::from_degrees(rng.gen_range(-5f32..5.));
+    if level.ground {
+        commands.spawn((
+            Name::new("Ground"),
+            Transform::default(),
+            RigidBody::Static,
+            Collider::segment(Vec2::new(-100000., 0.), Vec2::new(100000., 0.)),
+            LevelEntity,
+            StateScoped(screen.clone())
+        ));
+    }
+    for plank in &level.planks {
+        let color = match plank.color {
+            Some([r, g, b]) => Color::srgb(r, g, b),
+            None => wood(&plank.species_handle).random_color(rng),
+        };
         commands.trigger(SpawnPlank {
-            width: width,
-            height: 10.,
-            position: Vec2::new(pos_x, pos_y),
-            rotation,
+            shape: PlankShape::rectangle(plank.size.x, plank.size.y),
+            position: plank.position,
+            rotation: Rotation::from_degrees(plank.rotation),
             l_vel: None,
             a_vel: None,
-            color: Color::srgba(0.3 + rng.gen_range(-0.05..0.05),0.15 + rng.gen_range(-0.03..0.03),0.01, 1.)
+            color,
+            species: plank.species_handle.clone(),
+            grain: Grain(plank.grain.to_radians()),
+            joints: Vec::new(),
+            origin: None,
         });
     }
+    if let Some(pile) = &level.pile {
+        for i in 0..pile.count {
+            let pos_y = pile.position.y + (i as f32) * pile.size.y + rng.gen_range(1.5..3.5);
+            let pos_x = pile.position.x + rng.gen_range(-20f32..20.);
+            let width = pile.size.x + rng.gen_range(-25f32..5.);
+            let rotation = Rotation::from_degrees(rng.gen_range(-5f32..5.));
+            commands.trigger(SpawnPlank {
+                shape: PlankShape::rectangle(width, pile.size.y),
+                position: Vec2::new(pos_x, pos_y),
+                rotation,
+                l_vel: None,
+                a_vel: None,
+                color: wood(&pile.species_handle).random_color(rng),
+                species: pile.species_handle.clone(),
+                grain: Grain::default(),
+                joints: Vec::new(),
+                origin: None,
+            });
+        }
+    }
+    for prop in &level.props {
+        spawn_prop(&mut commands, prop, screen.clone());
+    }
+    if let Some(saw) = &level.saw {
+        spawn_saw(&mut commands, saw, screen.clone());
+    }
+    if !level.goals.is_empty() {
+        commands.label(level.goals.join("\n")).insert((
+            Name::new("Goals"),
+            LevelEntity,
+            StateScoped(screen),
+            Style {
+                position_type: PositionType::Absolute,
+                left: Px(10.0),
+                top: Px(80.0),
+                ..default()
+            },
+        ));
+    }
+}
 
-
+pub fn spawn_saw(commands: &mut Commands, saw: &SawDef, screen: Screen) {
     commands.spawn(
         (
-        Transform::from_xyz(0., 20.+SAW_HEIGHT, 0.),
+        Transform::from_xyz(saw.x, 20.+SAW_HEIGHT, 0.),
         RigidBody::Kinematic,
         Collider::rectangle(30.,20.),
         Name::new("Saw body"),
-            SawBody
+            SawBody,
+            LevelEntity,
+            StateScoped(screen.clone())
         )
     );
      commands.spawn((
-        Transform::from_xyz(0., 20. + SAW_HEIGHT, 0.),
+        Transform::from_xyz(saw.x, 20. + SAW_HEIGHT, 0.),
         RigidBody::Kinematic,
         Collider::triangle(-Vec2::X * 0.05, Vec2::X * 0.05, -Vec2::Y * 10.),
-        Saw { active: true },
+        Saw { active: true, angle: 0. },
         Sensor,
         Name::new("Saw"),
-                             StateScoped(Screen::Gameplay)
-    ));
-
-
-    // The only thing we have in our level is a player,
-    // but add things like walls etc. here.
+        LevelEntity,
+                             StateScoped(screen)
+    )).with_children(|children| {
+        children.spawn((
+            Text2dBundle {
+                text: Text::from_section(
+                    "0°",
+                    TextStyle {
+                        font_size: 8.,
+                        ..default()
+                    },
+                ),
+                transform: Transform::from_xyz(8., -5., 1.),
+                ..default()
+            },
+            SawAngleLabel,
+            Name::new("Saw angle"),
+        ));
+    });
 }
 
+pub fn spawn_prop(commands: &mut Commands, prop: &PropDef, screen: Screen) -> Entity {
+    let mut transform = Transform::from_translation(prop.position.extend(0.))
+        .with_rotation(Quat::from_rotation_z(prop.rotation.to_radians()));
+    transform.scale = Vec3::ONE * prop.scale;
 
-pub fn spawn_banana(mut commands: Commands, asset_server: Res<AssetServer>) {
-    let mut transform = Transform::from_xyz(-200., 600., 0.);
-    transform.scale = Vec3::ONE * 0.02;
-
-    let mut colliders = vec![];
-    colliders.push((Vec2::new(-275.,-150.), 85f32.to_radians(), Collider::capsule(150.,250.)));
-    colliders.push((Vec2::new(225.,-150.), 95f32.to_radians(), Collider::capsule(150.,250.)));
-    colliders.push((Vec2::new(400.,0.), -55f32.to_radians(), Collider::capsule(150.,400.)));
+    let colliders = prop
+        .colliders
+        .iter()
+        .map(|c| (c.offset, c.rotation.to_radians(), c.shape.collider()))
+        .collect();
     let collider = Collider::compound(colliders);
-        commands.spawn(
-        (SpriteBundle {
-            texture: asset_server.load("images/banana.png"),
+    let mut entity = commands.spawn((
+        SpriteBundle {
+            texture: prop.image_handle.clone(),
+            sprite: Sprite {
+                // Without an image, the prop is drawn by its parts.
+                color: if prop.image.is_some() { Color::WHITE } else { Color::NONE },
+                ..default()
+            },
             transform,
             ..default()
-        }, collider,
-            RigidBody::Dynamic,
-            Selectable,
-            MassPropertiesBundle::new_computed(&Collider::circle(1.), 1.),
-        StateScoped(Screen::Gameplay)
-        )
-    );
+        },
+        collider,
+        Name::new(prop.name.clone()),
+        RigidBody::Dynamic,
+        // The mass is given by a unit circle rather than the collider, so it doesn't depend
+        // on the image's scale. The collider adds none of its own.
+        MassPropertiesBundle::new_computed(&Collider::circle(1.), prop.mass / PI),
+        ColliderDensity(0.),
+        Prop(prop.clone()),
+        LevelEntity,
+        StateScoped(screen),
+    ));
+    if prop.selectable {
+        entity.insert(Selectable);
+    }
+    if prop.image.is_none() {
+        let [r, g, b] = prop.color.unwrap_or([0.5, 0.5, 0.5]);
+        entity.with_children(|children| {
+            for c in &prop.colliders {
+                children.spawn((
+                    Name::new("Prop part"),
+                    PropPart {
+                        shape: c.shape,
+                        color: Color::srgb(r, g, b),
+                    },
+                    SpatialBundle::from_transform(
+                        Transform::from_translation(c.offset.extend(0.))
+                            .with_rotation(Quat::from_rotation_z(c.rotation.to_radians())),
+                    ),
+                ));
+            }
+        });
+    }
+    entity.id()
+}
+
+/// A collider shape drawn in place of a prop's image.
+#[derive(Component)]
+pub struct PropPart {
+    pub shape: ShapeDef,
+    pub color: Color,
+}
+
+fn draw_prop_parts(
+    parts: Query<(Entity, &PropPart), Added<PropPart>>,
+    mut meshes: ResMut<Assets<Mesh>>,
+    mut materials: ResMut<Assets<ColorMaterial>>,
+    mut commands: Commands,
+) {
+    for (e, part) in &parts {
+        commands.entity(e).insert((
+            Mesh2dHandle(meshes.add(part.shape.mesh())),
+            materials.add(part.color),
+        ));
+    }
+}
 
-}
\ No newline at end of file
+/// Respawns the level when its file changes on disk.
+#[cfg(feature = "dev_native")]
+fn respawn_modified_level(
+    mut events: EventReader<AssetEvent<LevelDef>>,
+    current: Res<CurrentLevel>,
+    spawned: Query<Entity, Or<(With<Plank>, With<LevelEntity>)>>,
+    mut commands: Commands,
+) {
+    if !events.read().any(|e| e.is_modified(current.0.id())) {
+        return;
+    }
+    info!("Level file changed, respawning");
+    for entity in &spawned {
+        commands.entity(entity).despawn_recursive();
+    }
+    commands.add(spawn_level);
+}
@@ -0,0 +1,275 @@
+//! Where every plank came from: the stock plank its wood was cut from, and the cuts made on the
+//! way.
+//!
+//! Planks that weren't cut from another, like those a level starts with, are stock. Pieces cut
+//! from a plank inherit its [`PlankLineage`] along with the cut that made them. `H` shows the
+//! cuts made so far, grouped by stock plank.
+
+use crate::demo::level::Plank;
+use crate::demo::score::BuildStats;
+use crate::demo::shape::PlankShape;
+use crate::demo::wood::{PlankSpecies, WoodSpecies};
+use crate::demo::OnPlankSpawned;
+use crate::screens::Screen;
+use crate::theme::prelude::*;
+use avian2d::position::{Position, Rotation};
+use bevy::input::common_conditions::input_just_pressed;
+use bevy::prelude::*;
+use bevy::ui::Val::Px;
+use serde::{Deserialize, Serialize};
+use std::collections::BTreeMap;
+use std::time::Duration;
+
+pub(super) fn plugin(app: &mut App) {
+    app.init_resource::<LineageCounter>();
+    app.add_systems(OnExit(Screen::Gameplay), reset_counter);
+    app.add_systems(OnExit(Screen::Editor), reset_counter);
+    app.add_systems(OnEnter(Screen::Gameplay), spawn_history_panel);
+    app.add_systems(
+        Update,
+        (
+            toggle_history_panel.run_if(input_just_pressed(KeyCode::KeyH)),
+            update_history_panel,
+        )
+            .chain()
+            .run_if(in_state(Screen::Gameplay)),
+    );
+    app.observe(record_cut);
+    app.observe(inherit_lineage);
+}
+
+/// Where a plank's wood came from.
+#[derive(Component, Clone, Debug, Serialize, Deserialize)]
+pub struct PlankLineage {
+    /// Number of the stock plank the wood came from.
+    pub stock: usize,
+    /// Size of the stock plank.
+    pub stock_size: Vec2,
+    /// The piece this plank was cut from. It is no longer part of the build, as cutting uses it
+    /// up.
+    #[serde(skip)]
+    pub parent: Option<Entity>,
+    /// Where this plank's centre was on the stock plank, in the stock plank's local frame.
+    pub offset: Vec2,
+    /// The cuts made on the way from the stock plank to this one, oldest first.
+    pub cuts: Vec<CutRecord>,
+}
+
+/// A cut made in a plank.
+#[derive(Clone, Copy, Debug, Serialize, Deserialize, PartialEq)]
+pub struct CutRecord {
+    /// Cuts are numbered in the order they were made, so pieces sharing a cut can tell.
+    pub id: usize,
+    /// A point on the cut, in the stock plank's local frame.
+    pub position: Vec2,
+    /// Direction of the cut in degrees, counterclockwise from the stock plank's length, between
+    /// -90 and 90.
+    pub angle: f32,
+    /// Whether the cut followed a freehand path instead of a straight line.
+    pub freehand: bool,
+    /// How far into the build the cut was made.
+    pub time: Duration,
+}
+
+/// Triggered when a plank is about to be cut into pieces, before the pieces are spawned.
+#[derive(Event)]
+pub struct PlankCut {
+    pub plank: Entity,
+    /// A point on the cut, in the plank's local frame.
+    pub point: Vec2,
+    /// Direction of the cut in the plank's local frame.
+    pub direction: Vec2,
+    pub freehand: bool,
+    /// Area of wood the cut turns to sawdust.
+    pub sawdust: f32,
+}
+
+/// Numbers for the next stock plank and cut. Starts over with every level.
+#[derive(Resource, Default, Debug)]
+pub struct LineageCounter {
+    pub next_stock: usize,
+    pub next_cut: usize,
+}
+
+impl LineageCounter {
+    /// Makes sure numbers handed out from now on don't clash with `lineage`'s, e.g. when it
+    /// comes from a save.
+    pub fn skip_past(&mut self, lineage: &PlankLineage) {
+        self.next_stock = self.next_stock.max(lineage.stock + 1);
+        if let Some(last) = lineage.cuts.last() {
+            self.next_cut = self.next_cut.max(last.id + 1);
+        }
+    }
+}
+
+fn reset_counter(mut counter: ResMut<LineageCounter>) {
+    *counter = default();
+}
+
+fn record_cut(
+    trigger: Trigger<PlankCut>,
+    mut lineages: Query<&mut PlankLineage>,
+    mut counter: ResMut<LineageCounter>,
+    stats: Res<BuildStats>,
+    time: Res<Time>,
+) {
+    let ev = trigger.event();
+    let Ok(mut lineage) = lineages.get_mut(ev.plank) else {
+        return;
+    };
+    // A cut runs both ways, so its direction is kept within a half turn.
+    let mut angle = ev.direction.y.atan2(ev.direction.x).to_degrees();
+    if angle > 90. {
+        angle -= 180.;
+    } else if angle <= -90. {
+        angle += 180.;
+    }
+    let record = CutRecord {
+        id: counter.next_cut,
+        position: lineage.offset + ev.point,
+        angle,
+        freehand: ev.freehand,
+        time: time.elapsed().saturating_sub(stats.started),
+    };
+    counter.next_cut += 1;
+    lineage.cuts.push(record);
+}
+
+/// Gives a new plank the lineage of the plank it was cut from, or makes it a stock plank.
+fn inherit_lineage(
+    trigger: Trigger<OnPlankSpawned>,
+    lineages: Query<&PlankLineage>,
+    planks: Query<(&PlankShape, &Position, &Rotation), With<Plank>>,
+    mut counter: ResMut<LineageCounter>,
+    mut commands: Commands,
+) {
+    let ev = trigger.event();
+    // Planks restored from a save come with their lineage.
+    if lineages.contains(ev.plank) {
+        return;
+    }
+    let inherited = ev.origin.and_then(|origin| {
+        let parent = lineages.get(origin).ok()?;
+        let [(_, origin_pos, origin_rot), (_, position, _)] = planks.get_many([origin, ev.plank]).ok()?;
+        // Pieces keep the rotation of the plank they were cut from, so their frames only differ
+        // by where their centres are.
+        Some(PlankLineage {
+            parent: Some(origin),
+            offset: parent.offset + origin_rot.inverse() * (position.0 - origin_pos.0),
+            ..parent.clone()
+        })
+    });
+    let lineage = inherited.unwrap_or_else(|| {
+        let stock = counter.next_stock;
+        counter.next_stock += 1;
+        PlankLineage {
+            stock,
+            stock_size: planks.get(ev.plank).map_or(Vec2::ZERO, |(shape, ..)| shape.size()),
+            parent: None,
+            offset: Vec2::ZERO,
+            cuts: Vec::new(),
+        }
+    });
+    commands.entity(ev.plank).insert(lineage);
+}
+
+/// Text listing the cuts made so far. Hidden until `H` is pressed.
+#[derive(Component)]
+struct HistoryPanel;
+
+fn spawn_history_panel(mut commands: Commands) {
+    commands.label("").insert((
+        Name::new("Cut history"),
+        HistoryPanel,
+        StateScoped(Screen::Gameplay),
+        Visibility::Hidden,
+        Style {
+            position_type: PositionType::Absolute,
+            left: Px(10.0),
+            bottom: Px(120.0),
+            ..default()
+        },
+    ));
+}
+
+fn toggle_history_panel(mut panels: Query<&mut Visibility, With<HistoryPanel>>) {
+    for mut visibility in &mut panels {
+        *visibility = match *visibility {
+            Visibility::Hidden => Visibility::Inherited,
+            _ => Visibility::Hidden,
+        };
+    }
+}
+
+/// The cuts made in one stock plank.
+#[derive(Clone, Debug)]
+pub struct StockCuts {
+    pub size: Vec2,
+    /// Name of the stock plank's species.
+    pub species: String,
+    /// Oldest first.
+    pub cuts: Vec<CutRecord>,
+}
+
+/// The cuts made in every stock plank something is left of, by stock plank number. Cuts shared
+/// by several pieces are only listed once.
+pub fn cut_history<'a>(
+    lineages: impl IntoIterator<Item = (&'a PlankLineage, Option<&'a WoodSpecies>)>,
+) -> BTreeMap<usize, StockCuts> {
+    let mut history: BTreeMap<usize, StockCuts> = BTreeMap::new();
+    for (lineage, species) in lineages {
+        let stock = history.entry(lineage.stock).or_insert_with(|| StockCuts {
+            size: lineage.stock_size,
+            species: species.map_or_else(|| WoodSpecies::default().name, |s| s.name.clone()),
+            cuts: Vec::new(),
+        });
+        for cut in &lineage.cuts {
+            if !stock.cuts.iter().any(|c| c.id == cut.id) {
+                stock.cuts.push(*cut);
+            }
+        }
+    }
+    for stock in history.values_mut() {
+        stock.cuts.sort_by_key(|c| c.id);
+    }
+    history
+}
+
+fn update_history_panel(
+    planks: Query<(&PlankLineage, &PlankSpecies), With<Plank>>,
+    species: Res<Assets<WoodSpecies>>,
+    mut panels: Query<(&mut Text, &Visibility), With<HistoryPanel>>,
+) {
+    for (mut text, visibility) in &mut panels {
+        if *visibility == Visibility::Hidden {
+            continue;
+        }
+        let history = cut_history(planks.iter().map(|(l, s)| (l, species.get(&s.0))));
+        let mut lines = vec!["Cut history".to_string()];
+        for (number, stock) in history.iter().filter(|(_, s)| !s.cuts.is_empty()) {
+            lines.push(format!(
+                "Stock {} ({}, {:.0} x {:.0})",
+                number + 1,
+                stock.species,
+                stock.size.x,
+                stock.size.y
+            ));
+            for cut in &stock.cuts {
+                let secs = cut.time.as_secs();
+                // Measured from the stock plank's left end, like a tape measure would.
+                lines.push(format!(
+                    "  {}:{:02}  {} cut at {:.1}, {:.0}°",
+                    secs / 60,
+                    secs % 60,
+                    if cut.freehand { "Freehand" } else { "Straight" },
+                    cut.position.x + stock.size.x / 2.,
+                    cut.angle,
+                ));
+            }
+        }
+        if lines.len() == 1 {
+            lines.push("No cuts yet".to_string());
+        }
+        text.sections[0].value = lines.join("\n");
+    }
+}
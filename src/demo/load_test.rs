@@ -0,0 +1,248 @@
+//! Load testing a finished build by dropping the level's weights on it.
+//!
+//! `T` or the "Load test" button starts a test. Tools are put away while it runs, and the build
+//! fails if any of its joints break, a plank tips over or the build sags before the time is up.
+
+use crate::demo::blueprint::{main_build, BlueprintProgress};
+use crate::demo::history::History;
+use crate::demo::joint::{JointBroken, PlankJoint};
+use crate::demo::level::{spawn_prop, CurrentLevel, LevelDef, Plank};
+use crate::demo::{BuildPhase, DeselectAll, HeldTool};
+use crate::screens::Screen;
+use crate::theme::prelude::*;
+use avian2d::position::{Position, Rotation};
+use bevy::input::common_conditions::input_just_pressed;
+use bevy::prelude::*;
+use bevy::ui::Val::Px;
+use bevy::utils::HashMap;
+
+pub(super) fn plugin(app: &mut App) {
+    app.add_systems(OnEnter(Screen::Gameplay), spawn_test_button);
+    app.add_systems(OnEnter(BuildPhase::Testing), start_test);
+    app.add_systems(OnExit(BuildPhase::Testing), drop_unfinished_test);
+    app.add_systems(OnExit(Screen::Gameplay), |mut commands: Commands| {
+        commands.remove_resource::<LoadTest>();
+    });
+    app.add_systems(
+        Update,
+        (
+            drop_stale_test.run_if(resource_changed::<History>),
+            toggle_test.run_if(input_just_pressed(KeyCode::KeyT)),
+            watch_test.run_if(in_state(BuildPhase::Testing)),
+            update_test_label,
+        )
+            .chain()
+            .run_if(in_state(Screen::Gameplay)),
+    );
+    app.observe(count_broken_joints);
+}
+
+/// How far a plank may turn during the test before the build counts as tipped, in radians.
+const MAX_TILT: f32 = 0.5;
+/// How far the top of the build may sink during the test before it counts as collapsed.
+const MAX_SAG: f32 = 10.;
+/// Used when the level doesn't say how long its test lasts.
+const DEFAULT_DURATION: f32 = 10.;
+
+/// The load test in progress, or the last one run.
+#[derive(Resource)]
+pub struct LoadTest {
+    pub timer: Timer,
+    /// Planks of the build being tested.
+    pub build: Vec<Entity>,
+    /// Rotation of each plank of the build when the test started.
+    start_rotations: HashMap<Entity, Rotation>,
+    /// Height of the highest plank of the build when the test started.
+    start_top: f32,
+    pub broken_joints: usize,
+    pub tipped: bool,
+    pub collapsed: bool,
+    /// Set once the time is up.
+    pub outcome: Option<LoadTestOutcome>,
+}
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum LoadTestOutcome {
+    Passed,
+    Failed,
+}
+
+fn toggle_test(phase: Res<State<BuildPhase>>, mut next: ResMut<NextState<BuildPhase>>) {
+    next.set(match phase.get() {
+        BuildPhase::Building => BuildPhase::Testing,
+        BuildPhase::Testing => BuildPhase::Building,
+    });
+}
+
+fn press_test_button(
+    _trigger: Trigger<OnPress>,
+    phase: Res<State<BuildPhase>>,
+    next: ResMut<NextState<BuildPhase>>,
+) {
+    toggle_test(phase, next);
+}
+
+fn spawn_test_button(mut commands: Commands) {
+    commands
+        .spawn((
+            Name::new("Load test"),
+            StateScoped(Screen::Gameplay),
+            NodeBundle {
+                style: Style {
+                    position_type: PositionType::Absolute,
+                    flex_direction: FlexDirection::Column,
+                    align_items: AlignItems::End,
+                    row_gap: Px(10.0),
+                    right: Px(10.0),
+                    bottom: Px(40.0),
+                    ..default()
+                },
+                ..default()
+            },
+        ))
+        .with_children(|children| {
+            children.label("").insert(LoadTestLabel);
+            children.button("Load test").observe(press_test_button);
+        });
+}
+
+/// Puts the tools away, picks the build to test and drops the weights on it.
+fn start_test(
+    current: Res<CurrentLevel>,
+    levels: Res<Assets<LevelDef>>,
+    progress: Res<BlueprintProgress>,
+    planks: Query<(Entity, &Position, &Rotation), With<Plank>>,
+    joints: Query<&PlankJoint>,
+    tools: Query<Entity, With<HeldTool>>,
+    mut commands: Commands,
+) {
+    for tool in &tools {
+        commands.entity(tool).despawn_recursive();
+    }
+    commands.trigger(DeselectAll);
+
+    let build = main_build(&progress, planks.iter().map(|(e, ..)| e), joints.iter().map(|j| j.planks));
+
+    let mut start_rotations = HashMap::new();
+    let mut top = f32::NEG_INFINITY;
+    let mut x_sum = 0.;
+    for (e, position, rotation) in planks.iter_many(&build) {
+        start_rotations.insert(e, *rotation);
+        top = top.max(position.0.y);
+        x_sum += position.0.x;
+    }
+    let top_centre = Vec2::new(x_sum / build.len().max(1) as f32, top.max(0.));
+
+    let test = levels.get(&current.0).and_then(|l| l.load_test.as_ref());
+    if let Some(test) = test {
+        for weight in &test.weights {
+            let mut weight = weight.clone();
+            weight.position += top_centre;
+            let e = spawn_prop(&mut commands, &weight, Screen::Gameplay);
+            commands.entity(e).insert(StateScoped(BuildPhase::Testing));
+        }
+    }
+
+    commands.insert_resource(LoadTest {
+        timer: Timer::from_seconds(
+            test.map_or(DEFAULT_DURATION, |t| t.duration),
+            TimerMode::Once,
+        ),
+        build,
+        start_rotations,
+        start_top: top,
+        broken_joints: 0,
+        tipped: false,
+        collapsed: false,
+        outcome: None,
+    });
+}
+
+/// A test stopped before its time was up has no outcome, so it is forgotten.
+fn drop_unfinished_test(test: Option<Res<LoadTest>>, mut commands: Commands) {
+    if test.is_some_and(|t| t.outcome.is_none()) {
+        commands.remove_resource::<LoadTest>();
+    }
+}
+
+/// Every building action, undo and redo goes through the [`History`], so once it changes the
+/// last test no longer holds for the build.
+fn drop_stale_test(mut commands: Commands) {
+    commands.remove_resource::<LoadTest>();
+}
+
+fn count_broken_joints(trigger: Trigger<JointBroken>, test: Option<ResMut<LoadTest>>) {
+    let Some(mut test) = test else {
+        return;
+    };
+    // Joints breaking after the test is over don't count against it.
+    if test.outcome.is_none() && trigger.event().planks.iter().any(|p| test.build.contains(p)) {
+        test.broken_joints += 1;
+    }
+}
+
+fn watch_test(
+    time: Res<Time>,
+    planks: Query<(&Position, &Rotation), With<Plank>>,
+    mut test: ResMut<LoadTest>,
+) {
+    if test.outcome.is_some() {
+        return;
+    }
+    let mut top = f32::NEG_INFINITY;
+    let mut tipped = false;
+    for (e, start) in &test.start_rotations {
+        // Planks that were cut or lost since the start don't count.
+        let Ok((position, rotation)) = planks.get(*e) else {
+            continue;
+        };
+        top = top.max(position.0.y);
+        tipped |= (start.inverse() * *rotation).as_radians().abs() > MAX_TILT;
+    }
+    test.tipped |= tipped;
+    test.collapsed |= test.start_top - top > MAX_SAG;
+
+    if test.timer.tick(time.delta()).just_finished() {
+        let failed = test.broken_joints > 0 || test.tipped || test.collapsed;
+        test.outcome = Some(if failed {
+            LoadTestOutcome::Failed
+        } else {
+            LoadTestOutcome::Passed
+        });
+    }
+}
+
+/// Text showing the time left in the test and what has gone wrong so far, or how the last
+/// test went.
+#[derive(Component)]
+struct LoadTestLabel;
+
+fn update_test_label(test: Option<Res<LoadTest>>, mut labels: Query<&mut Text, With<LoadTestLabel>>) {
+    let text = test.map_or_else(String::new, |test| test_status(&test));
+    for mut label in &mut labels {
+        label.sections[0].value.clone_from(&text);
+    }
+}
+
+fn test_status(test: &LoadTest) -> String {
+    let mut problems = Vec::new();
+    if test.broken_joints > 0 {
+        problems.push(format!("{} joints broke", test.broken_joints));
+    }
+    if test.tipped {
+        problems.push("tipped over".to_string());
+    }
+    if test.collapsed {
+        problems.push("collapsed".to_string());
+    }
+    let status = match test.outcome {
+        None => format!("Testing: {:.0}s left", test.timer.remaining_secs().ceil()),
+        Some(LoadTestOutcome::Passed) => "Test passed!".to_string(),
+        Some(LoadTestOutcome::Failed) => "Test failed".to_string(),
+    };
+    if problems.is_empty() {
+        status
+    } else {
+        format!("{status} ({})", problems.join(", "))
+    }
+}
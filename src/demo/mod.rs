@@ -6,42 +6,112 @@
 use crate::demo::level::SawBody;
 use avian2d::prelude::LayerMask;
 use avian2d::prelude::CollisionLayers;
-use avian2d::prelude::{PhysicsLayer, Sensor};
+use avian2d::prelude::PhysicsLayer;
 use avian2d::prelude::Gravity;
-use crate::demo::level::{Plank, Saw};
+use crate::demo::fastener::FastenerKind;
+use crate::demo::history::{BuildAction, GrabPlanks, ParkPlank};
+use crate::demo::joint::{
+    driven_fastener_bundle, glue_line_bundle, joint_bundle, DrivenFastener, Fastenings, GlueLine, PlankJoint,
+};
+use crate::demo::level::{Plank, Saw, SawAngleLabel};
+use crate::demo::lineage::PlankCut;
+use crate::demo::seed::GameRng;
+use crate::demo::shop::{Stock, ToolUpgrade};
+use crate::demo::wood::{Grain, PlankSpecies, WoodSpecies};
+use crate::demo::shape::PlankShape;
 use crate::screens::Screen;
 use avian2d::collision::Collider;
 use avian2d::math::Scalar;
 use avian2d::position::Position;
 use avian2d::prelude::{RevoluteJoint, Rotation, Joint};
 use avian2d::prelude::{
-    AngularVelocity, CollidingEntities, ExternalForce, ExternalImpulse, LinearVelocity, RigidBody,
+    AngularVelocity, ColliderDensity, CollidingEntities, ExternalForce, ExternalImpulse, Friction,
+    LinearVelocity, MassPropertiesBundle, Restitution, RigidBody,
 };
+use bevy::ecs::query::QueryFilter;
 use bevy::prelude::*;
 use bevy::sprite::{MaterialMesh2dBundle, Mesh2dHandle};
 use rand::Rng;
 
+pub mod blueprint;
+pub mod campaign;
+pub mod drill;
+pub mod editor;
+pub mod export;
+pub mod fastener;
+pub mod glue;
+pub mod history;
+pub mod jigsaw;
+pub mod joint;
 pub mod level;
+pub mod lineage;
+pub mod load_test;
+pub mod save;
+pub mod score;
+pub mod seed;
+pub mod shape;
+pub mod shop;
+pub mod wood;
 
 pub(super) fn plugin(app: &mut App) {
-    app.add_plugins((level::plugin,));
+    // Plugin tuples only go up to 15, so tools and the rest are added separately.
+    app.add_plugins((
+        drill::plugin,
+        fastener::plugin,
+        glue::plugin,
+        jigsaw::plugin,
+        joint::plugin,
+    ));
+    app.add_plugins((
+        blueprint::plugin,
+        campaign::plugin,
+        editor::plugin,
+        export::plugin,
+        history::plugin,
+        level::plugin,
+        lineage::plugin,
+        load_test::plugin,
+        save::plugin,
+        score::plugin,
+        seed::plugin,
+        shop::plugin,
+        wood::plugin,
+    ));
     app.insert_resource(Gravity(Vec2::NEG_Y * 100.));
     app.insert_resource(PrevMousePos { pos: Vec2::ZERO});
-    app.add_systems(FixedUpdate, move_saw.run_if(in_state(Screen::Gameplay)));
-    app.add_systems(FixedUpdate, split.run_if(in_state(Screen::Gameplay)));
-    app.add_systems(Update, selection_system.run_if(in_state(Screen::Gameplay)));
-    app.add_systems(Update, move_with_mouse.run_if(in_state(Screen::Gameplay)));
-    app.add_systems(Update, nail_system);
+    app.add_sub_state::<BuildPhase>();
+    app.enable_state_scoped_entities::<BuildPhase>();
+    app.add_systems(FixedUpdate, move_saw.run_if(in_state(BuildPhase::Building)));
+    app.add_systems(FixedUpdate, split.run_if(in_state(BuildPhase::Building)));
+    app.add_systems(Update, (tilt_saw, update_saw_angle_label).chain().run_if(in_state(BuildPhase::Building)));
+    app.add_systems(Update, selection_system.run_if(in_state(BuildPhase::Building)));
+    app.add_systems(Update, delete_planks.run_if(in_state(BuildPhase::Building)));
+    app.add_systems(Update, move_with_mouse.run_if(in_state(BuildPhase::Building)));
     app.observe(spawn_plank);
     app.observe(deselect_all);
 }
 
+/// What the player is doing during gameplay. Tools only work while building.
+#[derive(SubStates, Debug, Hash, PartialEq, Eq, Clone, Default)]
+#[source(Screen = Screen::Gameplay)]
+pub enum BuildPhase {
+    #[default]
+    Building,
+    /// The build is being load tested, see [`load_test`].
+    Testing,
+}
+
 #[derive(Component)]
 pub struct Selected;
 
 #[derive(Event)]
 pub struct DeselectAll;
 
+/// Marks the tool following the cursor. Only one tool can be in hand at a time, and while one
+/// is, clicking uses it instead of grabbing planks.
+#[derive(Component)]
+pub struct HeldTool;
+
 #[derive(Resource)]
 pub struct PrevMousePos {
     pos: Vec2
@@ -53,39 +123,6 @@ pub enum GameLayers {
     Ground
 }
 
-#[derive(Component)]
-pub struct Nail;
-
-fn nail_system(input: Res<ButtonInput<KeyCode>>, mut nail: Query<(Entity, &mut Position, &mut Rotation), With<Nail>>, mut commands: Commands
-               ,
-               window: Query<&Window>, camera: Query<(&Camera, &GlobalTransform)>
-) {
-    if input.just_pressed(KeyCode::KeyN) {
-        if nail.is_empty() {
-            commands.spawn(
-                (Nail, Transform::from_xyz(0., 200., 0.),
-                 Sensor,
-                 Collider::segment(Vec2::Y * 2.5, -Vec2::Y * 7.5)),
-            );
-            // Spawn Nail, at cursor
-        } else {
-            let (e,_,_) = nail.get_single().unwrap();
-            commands.entity(e).despawn();
-
-        }
-    }
-    if let Ok((e, mut nail, mut rotation)) = nail.get_single_mut() {
-        let window = window.get_single().unwrap();
-        let (camera, gt) = camera.get_single().unwrap();
-        if let Some(p) = get_world_pos(window, camera, gt) {
-            nail.0 = p.truncate()
-        }
-        if input.pressed(KeyCode::KeyR) {
-            *rotation = rotation.add_angle(-1f32.to_radians());
-        }
-    }
-}
-
 fn deselect_all(_: Trigger<DeselectAll>, selected: Query<Entity, With<Selected>>, mut commands: Commands) {
     for e in selected.iter() {
         commands.entity(e).despawn();
@@ -108,49 +145,100 @@ fn get_world_pos(window: &Window, camera: &Camera, gt: &GlobalTransform) -> Opti
     window.cursor_position().and_then(|c| camera.viewport_to_world(gt, c)).map(|r| r.origin)
 }
 
+/// Dragging pulls on planks through a soft joint, like a spring, so a player can load a
+/// build by hand and see if its joints hold.
+const DRAG_COMPLIANCE: f32 = 1e-5;
+
 fn selection_system(
     window: Query<&Window>,
     camera: Query<(&Camera, &GlobalTransform)>,
     selectables: Query<(Entity, &Collider, &Position, &Rotation), With<Selectable>>,
+    planks: Query<(), With<Plank>>,
     input: Res<ButtonInput<MouseButton>>,
+    held_tool: Query<(), With<HeldTool>>,
     mut commands: Commands
 ) {
     if input.just_released(MouseButton::Left) {
         commands.trigger(DeselectAll);
     }
-    if !input.just_pressed(MouseButton::Left) {
+    // Clicking uses the tool in hand instead of grabbing planks.
+    if !input.just_pressed(MouseButton::Left) || !held_tool.is_empty() {
         return;
     }
     let (camera, transform) = camera.get_single().unwrap();
     let window = window.get_single().unwrap();
     if let Some(pos) = get_world_pos(window, camera, transform)
     {
-        for (e, c, p, r) in selectables.iter() {
-            if c.contains_point(*p, *r, pos.truncate()) {
-                let new_ent = commands.spawn(
-                    (
-                        RigidBody::Dynamic,
-                        Collider::circle(2.),
-                        Transform::from_translation(pos),
-                        CollisionLayers::new(GameLayers::Objects, GameLayers::Ground),
-                    Selected)
-                ).id();
-                let mut local_pos = pos.truncate() - p.0;
-                local_pos = (*r).inverse() * local_pos;
-                commands.spawn((Selected, RevoluteJoint::new(e, new_ent).with_local_anchor_1(local_pos).with_compliance(0.)));
-            }
+        let picked = pick_at(pos.truncate(), &selectables);
+        // Props can be moved too, but only planks are part of the build to undo.
+        let grabbed: Vec<Entity> = picked.iter().map(|(e, _)| *e).filter(|e| planks.contains(*e)).collect();
+        if !grabbed.is_empty() {
+            commands.trigger(GrabPlanks(grabbed));
+        }
+        for (e, local_pos) in picked {
+            let new_ent = commands.spawn(
+                (
+                    RigidBody::Dynamic,
+                    Collider::circle(2.),
+                    Transform::from_translation(pos),
+                    CollisionLayers::new(GameLayers::Objects, GameLayers::Ground),
+                Selected)
+            ).id();
+            commands.spawn((Selected, RevoluteJoint::new(e, new_ent).with_local_anchor_1(local_pos).with_compliance(DRAG_COMPLIANCE)));
         }
     }
 }
 
+/// Takes the planks under the cursor out of the build, so deleting them can be undone.
+fn delete_planks(
+    window: Query<&Window>,
+    camera: Query<(&Camera, &GlobalTransform)>,
+    planks: Query<(Entity, &Collider, &Position, &Rotation), With<Plank>>,
+    input: Res<ButtonInput<KeyCode>>,
+    mut commands: Commands,
+) {
+    if !input.any_just_pressed([KeyCode::Delete, KeyCode::Backspace]) {
+        return;
+    }
+    let (camera, transform) = camera.get_single().unwrap();
+    let window = window.get_single().unwrap();
+    let Some(pos) = get_world_pos(window, camera, transform) else {
+        return;
+    };
+    let picked: Vec<Entity> = pick_at(pos.truncate(), &planks).into_iter().map(|(e, _)| e).collect();
+    if picked.is_empty() {
+        return;
+    }
+    // Let go of the planks first, in case they are being dragged.
+    commands.trigger(DeselectAll);
+    commands.trigger(BuildAction::new("Delete", picked.iter().copied()));
+    for e in picked {
+        commands.add(ParkPlank(e));
+    }
+}
+
+/// The entities whose colliders contain `point`, and where `point` is in each one's local frame.
+fn pick_at<F: QueryFilter>(
+    point: Vec2,
+    candidates: &Query<(Entity, &Collider, &Position, &Rotation), F>,
+) -> Vec<(Entity, Vec2)> {
+    candidates
+        .iter()
+        .filter(|(_, c, p, r)| c.contains_point(**p, **r, point))
+        .map(|(e, _, p, r)| (e, r.inverse() * (point - p.0)))
+        .collect()
+}
+
 pub const SAW_HEIGHT: f32 = 10.;
 
 fn move_saw(
-    mut query: Query<(&mut LinearVelocity, &mut Position, &mut Saw), Without<SawBody>>,
+    mut query: Query<(&mut LinearVelocity, &mut Position, &Rotation, &CollidingEntities, &mut Saw), Without<SawBody>>,
     mut saw_body: Query<(&mut LinearVelocity, &mut Position), (With<SawBody>, Without<Saw>)>,
+    planks: Query<(&Rotation, &Grain), (With<Plank>, Without<Saw>)>,
     input: Res<ButtonInput<KeyCode>>,
+    mut rng: ResMut<GameRng>,
 ) {
-    if let (Ok((mut velocity, mut position, mut saw)), Ok((mut saw_body_vel, mut saw_body_pos))) = (query.get_single_mut(), saw_body.get_single_mut()) {
+    if let (Ok((mut velocity, mut position, saw_rot, collisions, mut saw)), Ok((mut saw_body_vel, mut saw_body_pos))) = (query.get_single_mut(), saw_body.get_single_mut()) {
         let prev_velocity = *velocity;
         let prev_position = *position;
         let mut movement = 0.;
@@ -194,7 +282,7 @@ fn move_saw(
         if input.pressed(KeyCode::Space) {
             let jitter = if position.y > SAW_HEIGHT + 13. { 0.6 } else { 0.1 };
             if position.y > 10.5 {
-                position.x += rand::thread_rng().gen_range(-jitter..jitter);
+                position.x += rng.0.gen_range(-jitter..jitter);
             }
             if position.y > 10. {
                 velocity.y -= 0.2;
@@ -204,6 +292,16 @@ fn move_saw(
         } else if position.y < SAW_HEIGHT + 19.5 {
             velocity.y += 0.5;
         }
+        // The blade can't go down faster than it cuts, and it cuts slowest across the grain.
+        if saw.active {
+            let feed = planks
+                .iter_many(&collisions.0)
+                .map(|(r, grain)| grain.saw_feed(r.inverse() * (*saw_rot * Vec2::Y)))
+                .reduce(f32::min);
+            if let Some(feed) = feed {
+                velocity.y = velocity.y.max(-feed);
+            }
+        }
         let vel_change = velocity.0 - prev_velocity.0;
         let pos_change = position.0 - prev_position.0;
         saw_body_vel.0.x += vel_change.x;
@@ -215,15 +313,89 @@ fn move_saw(
     }
 }
 
+/// The steepest bevel the saw can be tilted to, in degrees.
+pub const MAX_SAW_ANGLE: f32 = 45.;
+const SAW_ANGLE_STEP: f32 = 5.;
+
+fn tilt_saw(
+    mut saw: Query<(&Position, &mut Rotation, &mut Saw)>,
+    input: Res<ButtonInput<KeyCode>>,
+    stock: Res<Stock>,
+) {
+    let Ok((position, mut rotation, mut saw)) = saw.get_single_mut() else {
+        return;
+    };
+    // The blade can only be adjusted while it is fully raised.
+    if position.y < SAW_HEIGHT + 19. {
+        return;
+    }
+    let angle_step = if stock.has(ToolUpgrade::BevelGauge) { 1. } else { SAW_ANGLE_STEP };
+    let mut step = 0.;
+    if input.just_pressed(KeyCode::KeyQ) {
+        step -= angle_step;
+    }
+    if input.just_pressed(KeyCode::KeyE) {
+        step += angle_step;
+    }
+    if step != 0. {
+        saw.angle = (saw.angle + step).clamp(-MAX_SAW_ANGLE, MAX_SAW_ANGLE);
+        *rotation = Rotation::from_degrees(-saw.angle);
+    }
+}
+
+fn update_saw_angle_label(saw: Query<&Saw, Changed<Saw>>, mut labels: Query<&mut Text, With<SawAngleLabel>>) {
+    let Ok(saw) = saw.get_single() else {
+        return;
+    };
+    for mut text in &mut labels {
+        text.sections[0].value = format!("{:.0}°", saw.angle);
+    }
+}
+
+/// Spawns a plank. Trigger it on an existing entity to turn that entity into the plank.
+///
+/// [`OnPlankSpawned`] is triggered once the plank is built.
 #[derive(Event)]
 pub struct SpawnPlank {
-    width: f32,
-    height: f32,
+    shape: PlankShape,
     position: Vec2,
     rotation: Rotation,
     l_vel: Option<LinearVelocity>,
     a_vel: Option<AngularVelocity>,
     color: Color,
+    species: Handle<WoodSpecies>,
+    grain: Grain,
+    /// Fasteners and glue lines joining the new plank to existing ones.
+    joints: Vec<ParentJoint>,
+    /// The plank this one was made from, if any.
+    origin: Option<Entity>,
+}
+
+/// A fastener or glue line joining a newly spawned plank to an existing one. Like every joint,
+/// it only holds during gameplay.
+#[derive(Clone, Copy, Debug)]
+pub struct ParentJoint {
+    pub parent: Entity,
+    /// Where the fastener or the middle of the glue line goes, in world space.
+    pub position: Vec2,
+    pub fastening: Fastening,
+}
+
+/// What holds a [`ParentJoint`] together.
+#[derive(Clone, Copy, Debug)]
+pub enum Fastening {
+    Fastener { kind: FastenerKind, rotation: Rotation },
+    /// A line of cured glue, `length` long.
+    Glue { length: f32 },
+}
+
+/// Triggered when a [`SpawnPlank`] has been built, to tell whoever asked for it which entity
+/// the plank is.
+#[derive(Event, Clone, Copy, Debug)]
+pub struct OnPlankSpawned {
+    pub plank: Entity,
+    /// The [`SpawnPlank::origin`] of the plank, e.g. the plank it was cut from.
+    pub origin: Option<Entity>,
 }
 
 #[derive(Component, Default)]
@@ -234,23 +406,61 @@ pub struct ColorInfo(Color);
 
 const CUT_SIZE: f32 = 1.;
 
-fn spawn_plank(trigger: Trigger<SpawnPlank>, mut commands: Commands, mut meshes: ResMut<Assets<Mesh>>, mut materials: ResMut<Assets<ColorMaterial>>) {
+fn spawn_plank(
+    trigger: Trigger<SpawnPlank>,
+    mut commands: Commands,
+    mut meshes: ResMut<Assets<Mesh>>,
+    mut materials: ResMut<Assets<ColorMaterial>>,
+    species: Res<Assets<WoodSpecies>>,
+    planks: Query<(&Position, &Rotation), With<Plank>>,
+    screen: Res<State<Screen>>,
+) {
     let ev = trigger.event();
-    let collider = Collider::rectangle(ev.width, ev.height);
-    let mut ent_commands = commands.spawn((
+    let Some(collider) = ev.shape.collider() else {
+        warn!("Skipping plank with a degenerate shape");
+        return;
+    };
+    let default_species = WoodSpecies::default();
+    let wood = species.get(&ev.species).unwrap_or(&default_species);
+    // Triggering the event on an entity builds the plank on that entity, so the caller knows
+    // which entity it is.
+    let mut ent_commands = if trigger.entity() == Entity::PLACEHOLDER {
+        commands.spawn_empty()
+    } else {
+        commands.entity(trigger.entity())
+    };
+    // The species' grain texture, tinted with the plank's colour. Every plank gets its own
+    // material, as glue tints it in place.
+    let material = materials.add(ColorMaterial {
+        color: ev.color,
+        texture: Some(wood.grain_handle.clone()),
+    });
+    ent_commands.insert((
         RigidBody::Dynamic,
+        // The plank's mass comes from its species, not from the collider's default density.
+        MassPropertiesBundle::new_computed(&collider, wood.density),
+        ColliderDensity(0.),
+        Friction::new(wood.friction),
+        Restitution::new(wood.restitution),
+        PlankSpecies(ev.species.clone()),
+        ev.grain,
         collider,
         MaterialMesh2dBundle {
-            mesh: Mesh2dHandle(meshes.add(Rectangle::new(ev.width, ev.height))),
-            transform: Transform::from_translation(ev.position.extend(0.)),
-            material: materials.add(ev.color),
+            mesh: Mesh2dHandle(meshes.add(ev.shape.mesh(ev.grain.direction()))),
+            transform: Transform::from_translation(ev.position.extend(0.))
+                .with_rotation(Quat::from_rotation_z(ev.rotation.as_radians())),
+            material,
             ..default()
         },
         Plank,
+        ev.shape.clone(),
         ColorInfo(ev.color),
         Selectable::default(),
         CollisionLayers::new(GameLayers::Objects, LayerMask::ALL),
-        StateScoped(Screen::Gameplay)
+        Position(ev.position),
+        ev.rotation,
+        // Planks belong to the screen they were spawned on, gameplay or the editor.
+        StateScoped(screen.get().clone())
     ));
     if let Some(l) = ev.l_vel {
         ent_commands.insert(l);
@@ -258,70 +468,216 @@ fn spawn_plank(trigger: Trigger<SpawnPlank>, mut commands: Commands, mut meshes:
     if let Some(a) = ev.a_vel {
         ent_commands.insert(a);
     }
+    let plank = ent_commands.id();
+
+    // One joint per parent plank, anchored at its first fastening.
+    let mut parents: Vec<Entity> = ev.joints.iter().map(|j| j.parent).collect();
+    parents.sort();
+    parents.dedup();
+    for parent in parents {
+        let Ok((parent_pos, parent_rot)) = planks.get(parent) else {
+            warn!("Not joining the new plank to {parent:?}, which isn't a plank");
+            continue;
+        };
+        let fastenings: Vec<&ParentJoint> = ev.joints.iter().filter(|j| j.parent == parent).collect();
+        let anchor = fastenings[0].position;
+        let local_anchors = |position: Vec2| {
+            [
+                parent_rot.inverse() * (position - parent_pos.0),
+                ev.rotation.inverse() * (position - ev.position),
+            ]
+        };
+        commands
+            .spawn(joint_bundle(PlankJoint {
+                planks: [parent, plank],
+                local_anchors: local_anchors(anchor),
+                brace_anchors: None,
+                compliance: 0.,
+            }))
+            .with_children(|children| {
+                for fastening in fastenings {
+                    match fastening.fastening {
+                        Fastening::Fastener { kind, rotation } => {
+                            children.spawn(driven_fastener_bundle(
+                                kind,
+                                // Placed on the plank by the joint systems.
+                                Transform::from_xyz(0., 0., 1.),
+                                DrivenFastener {
+                                    plank,
+                                    local_position: ev.rotation.inverse() * (fastening.position - ev.position),
+                                    local_angle: rotation.as_radians() - ev.rotation.as_radians(),
+                                },
+                            ));
+                        }
+                        Fastening::Glue { length } => {
+                            children.spawn(glue_line_bundle(GlueLine {
+                                length,
+                                local_anchors: local_anchors(fastening.position),
+                            }));
+                        }
+                    }
+                }
+            });
+    }
+    commands.trigger(OnPlankSpawned {
+        plank,
+        origin: ev.origin,
+    });
+}
+
+impl SpawnPlank {
+    /// Whether `point`, in world space, lands in the plank once it is spawned.
+    fn holds(&self, point: Vec2) -> bool {
+        self.shape.contains(self.rotation.inverse() * (point - self.position))
+    }
+}
+
+/// A plank being cut, with the pieces it is cut into.
+struct CutPlank {
+    plank: Entity,
+    /// The fastenings holding the plank, from [`Fastenings::of`].
+    held: Vec<ParentJoint>,
+    /// The pieces, without joints.
+    pieces: Vec<SpawnPlank>,
+}
+
+/// Spawns the pieces of planks cut together, then parks the planks.
+///
+/// Pieces stay fastened to whatever held their plank where they weren't cut. Two planks fastened
+/// to each other and cut together are joined piece to piece: the pieces of the plank cut later
+/// join the pieces of the other that still hold each fastener, which are spawned by then.
+fn spawn_pieces(commands: &mut Commands, cuts: Vec<CutPlank>) {
+    // The pieces get their entities up front, so pieces of one plank can be joined to those of
+    // another before either is spawned.
+    let entities: Vec<Vec<Entity>> = cuts
+        .iter()
+        .map(|cut| cut.pieces.iter().map(|_| commands.spawn_empty().id()).collect())
+        .collect();
+    let joints: Vec<Vec<Vec<ParentJoint>>> = cuts
+        .iter()
+        .enumerate()
+        .map(|(i, cut)| {
+            cut.pieces
+                .iter()
+                .map(|piece| {
+                    cut.held
+                        .iter()
+                        .filter(|f| piece.holds(f.position))
+                        .filter_map(|f| match cuts.iter().position(|c| c.plank == f.parent) {
+                            None => Some(*f),
+                            // The parent's pieces pick this fastening up themselves.
+                            Some(k) if k >= i => None,
+                            Some(k) => {
+                                let parent = cuts[k].pieces.iter().position(|p| p.holds(f.position))?;
+                                Some(ParentJoint {
+                                    parent: entities[k][parent],
+                                    ..*f
+                                })
+                            }
+                        })
+                        .collect()
+                })
+                .collect()
+        })
+        .collect();
+
+    let planks: Vec<Entity> = cuts.iter().map(|cut| cut.plank).collect();
+    for ((cut, entities), joints) in cuts.into_iter().zip(entities).zip(joints) {
+        for ((piece, e), joints) in cut.pieces.into_iter().zip(entities).zip(joints) {
+            commands.trigger_targets(SpawnPlank { joints, ..piece }, e);
+        }
+    }
+    // Only parked once every piece is spawned, as the pieces are joined to their parents and
+    // inherit from their origins as they spawn.
+    for plank in planks {
+        commands.add(ParkPlank(plank));
+    }
+}
+
+/// Velocity of the point `offset` away from the center of mass of a body moving with the given
+/// velocities, so pieces cut from a moving plank keep moving with it.
+fn point_velocity(linear: Vec2, angular: Scalar, offset: Vec2) -> LinearVelocity {
+    LinearVelocity(linear + offset.perp() * angular)
 }
 
 fn split(
-    mut saw: Query<(&Position, &CollidingEntities, &mut Saw)>,
-    planks: Query<(&Collider, &Position, &ColorInfo), With<Plank>>,
+    mut saw: Query<(&Position, &Rotation, &CollidingEntities, &mut Saw)>,
+    planks: Query<
+        (
+            &PlankShape,
+            &Position,
+            &Rotation,
+            Option<&LinearVelocity>,
+            Option<&AngularVelocity>,
+            &ColorInfo,
+            &PlankSpecies,
+            &Grain,
+        ),
+        With<Plank>,
+    >,
+    fastenings: Fastenings,
+    stock: Res<Stock>,
     mut commands: Commands,
 ) {
     if saw.is_empty() {
         return;
     }
-    let (saw_pos, collisions, mut saw) = saw.get_single_mut().unwrap();
+    let (saw_pos, saw_rot, collisions, mut saw) = saw.get_single_mut().unwrap();
     if !saw.active {
         return;
     }
     if saw_pos.y > 11. {
         return;
     }
-    for collision in &collisions.0 {
-        if let Ok((c, p, color_info)) = planks.get(*collision) {
-            saw.active = false;
-            let relative_x = saw_pos.x - p.x;
-            let cuboid = c.shape().as_cuboid().unwrap();
-            let old_collider_width = cuboid.half_extents.x as f32 * 2.;
-            let collider_height = cuboid.half_extents.y as f32 * 2.;
-            let new_collider_left_width = old_collider_width / 2. + relative_x - CUT_SIZE;
-            let new_collider_right_width = old_collider_width / 2. - relative_x - CUT_SIZE;
-            if new_collider_left_width > 0. {
-                let pos_left = Vec2::new(saw_pos.x - new_collider_left_width / 2. - CUT_SIZE, p.y);
-                let angular = AngularVelocity(1. / new_collider_left_width.log2());
-                let linear = LinearVelocity(Vec2::new(
-                    -10. / new_collider_left_width.log2(),
-                    10. / new_collider_left_width.log2(),
-                ));
-                let command_left = SpawnPlank {
-                    width: new_collider_left_width,
-                    height: collider_height,
-                    position: pos_left,
-                    rotation: Rotation::default(),
-                    l_vel: Some(linear),
-                    a_vel: Some(angular),
-                    color: color_info.0
-                };
-                commands.trigger(command_left);
-            }
-
-            if new_collider_right_width > 0. {
-                let pos_left = Vec2::new(saw_pos.x + new_collider_right_width / 2. - CUT_SIZE, p.y);
-                let angular = AngularVelocity(-1. / new_collider_right_width.log2());
-                let linear = LinearVelocity(Vec2::new(
-                    10. / new_collider_right_width.log2(),
-                    10. / new_collider_right_width.log2(),
-                ));
-                let command_left = SpawnPlank {
-                    width: new_collider_right_width,
-                    height: collider_height,
-                    position: pos_left,
-                    rotation: Rotation::default(),
-                    l_vel: Some(linear),
-                    a_vel: Some(angular),
-                    color: color_info.0
-                };
-                commands.trigger(command_left);
-            }
-            commands.entity(*collision).despawn();
+    let cut: Vec<Entity> = collisions.0.iter().copied().filter(|e| planks.contains(*e)).collect();
+    if cut.is_empty() {
+        return;
+    }
+    saw.active = false;
+    // One action covers every plank the blade went through.
+    commands.trigger(BuildAction::new("Cut", cut.iter().copied()));
+    let mut cuts = Vec::new();
+    for collision in &cut {
+        if let Ok((shape, p, r, l_vel, a_vel, color_info, species, grain)) = planks.get(*collision) {
+            let linear = l_vel.map_or(Vec2::ZERO, |v| v.0);
+            let angular = a_vel.map_or(0., |v| v.0);
+            // Work in the plank's local frame: the cut is the line through the saw along the
+            // tilted blade.
+            let local_saw = r.inverse() * (saw_pos.0 - p.0);
+            let local_normal = r.inverse() * (*saw_rot * Vec2::X);
+            let kerf = Grain::kerf(stock.kerf(), grain.across(local_normal.perp()));
+            let pieces = shape.split(local_saw, local_normal, kerf);
+            commands.trigger(PlankCut {
+                plank: *collision,
+                point: local_saw,
+                direction: local_normal.perp(),
+                freehand: false,
+                sawdust: shape.sawdust(pieces.iter().map(|(piece, _)| piece)),
+            });
+            let pieces = pieces
+                .into_iter()
+                .map(|(piece, local_offset)| {
+                    let offset = *r * local_offset;
+                    SpawnPlank {
+                        shape: piece,
+                        position: p.0 + offset,
+                        rotation: *r,
+                        l_vel: Some(point_velocity(linear, angular, offset)),
+                        a_vel: Some(AngularVelocity(angular)),
+                        color: color_info.0,
+                        species: species.0.clone(),
+                        grain: *grain,
+                        joints: Vec::new(),
+                        origin: Some(*collision),
+                    }
+                })
+                .collect();
+            cuts.push(CutPlank {
+                plank: *collision,
+                held: fastenings.of(*collision),
+                pieces,
+            });
         }
     }
+    spawn_pieces(&mut commands, cuts);
 }
@@ -0,0 +1,523 @@
+//! Saving the workshop mid-build and restoring it exactly, planks, joints and saw included.
+//!
+//! `F5` saves and `F9` loads during gameplay. Native builds keep saves in the user data
+//! directory, web builds in the browser's local storage. Other progress, like the campaign,
+//! is stored the same way with [`write_save`] and [`read_save`].
+
+use crate::demo::campaign::ActiveOrder;
+use crate::demo::fastener::{Fastener, FastenerKind};
+use crate::demo::glue::Glue;
+use crate::demo::history::{BuildAction, ParkPlank};
+use crate::demo::joint::{
+    driven_fastener_bundle, glue_line_bundle, joint_bundle, DrivenFastener, GlueLine, PlankJoint,
+};
+use crate::demo::level::{CurrentLevel, LevelDef, Plank, Saw, SawBody};
+use crate::demo::lineage::{LineageCounter, PlankLineage};
+use crate::demo::score::BuildStats;
+use crate::demo::seed::LevelSeed;
+use crate::demo::shape::PlankShape;
+use crate::demo::wood::{Grain, PlankSpecies, WoodAssets};
+use crate::demo::{BuildPhase, ColorInfo, SpawnPlank};
+use avian2d::position::{Position, Rotation};
+use avian2d::prelude::{AngularVelocity, LinearVelocity};
+use bevy::ecs::system::SystemParam;
+use bevy::ecs::world::Command;
+use bevy::input::common_conditions::input_just_pressed;
+use bevy::prelude::*;
+use bevy::utils::HashMap;
+use serde::{Deserialize, Serialize};
+use std::time::Duration;
+
+pub(super) fn plugin(app: &mut App) {
+    app.add_systems(
+        Update,
+        (
+            save_workshop.run_if(input_just_pressed(KeyCode::F5)),
+            load_workshop.run_if(input_just_pressed(KeyCode::F9)),
+        )
+            .run_if(in_state(BuildPhase::Building)),
+    );
+}
+
+/// Everything needed to rebuild the workshop as it was.
+#[derive(Serialize, Deserialize, Debug)]
+pub struct WorkshopSave {
+    pub planks: Vec<PlankSave>,
+    pub joints: Vec<JointSave>,
+    pub saw: Option<SawSave>,
+    /// The level the workshop was saved in. Saves without it continue in the current level.
+    #[serde(default)]
+    pub level: Option<LevelSave>,
+}
+
+/// Which level was being played, and how far the build had come.
+#[derive(Serialize, Deserialize, Debug)]
+pub struct LevelSave {
+    /// Path of the level, relative to the assets folder.
+    pub path: String,
+    /// Path of the campaign order's level, if an order was being worked on.
+    pub order: Option<String>,
+    pub seed: u64,
+    pub stats: BuildStats,
+    /// Seconds spent building so far.
+    pub build_secs: f32,
+}
+
+#[derive(Serialize, Deserialize, Debug)]
+pub struct PlankSave {
+    pub shape: PlankShape,
+    pub position: Vec2,
+    /// Rotation in radians.
+    pub rotation: f32,
+    pub linear_velocity: Vec2,
+    pub angular_velocity: f32,
+    /// sRGBA colour.
+    pub color: [f32; 4],
+    /// Spots of glue that hasn't bonded anything yet, in the plank's local frame.
+    #[serde(default)]
+    pub glue: Vec<Vec2>,
+    /// Path of the plank's [`WoodSpecies`](crate::demo::wood::WoodSpecies).
+    #[serde(default)]
+    pub species: Option<String>,
+    /// Direction of the grain in radians, from the plank's local x axis.
+    #[serde(default)]
+    pub grain: f32,
+    /// Where the plank was cut from. Saves without it treat the plank as stock.
+    #[serde(default)]
+    pub lineage: Option<PlankLineage>,
+}
+
+#[derive(Serialize, Deserialize, Debug)]
+pub struct JointSave {
+    /// Indices into [`WorkshopSave::planks`].
+    pub planks: [usize; 2],
+    pub local_anchors: [Vec2; 2],
+    pub brace_anchors: Option<[Vec2; 2]>,
+    pub fasteners: Vec<FastenerSave>,
+    /// Lengths of the cured glue lines in the joint.
+    pub glue_lines: Vec<f32>,
+    /// Where each glue line is, like [`JointSave::local_anchors`]. Saves without them put the
+    /// glue lines at the joint's anchors.
+    #[serde(default)]
+    pub glue_anchors: Vec<[Vec2; 2]>,
+}
+
+#[derive(Serialize, Deserialize, Debug)]
+pub struct FastenerSave {
+    pub kind: FastenerKind,
+    /// Index into [`WorkshopSave::planks`] of the plank the fastener was driven into.
+    pub plank: usize,
+    pub local_position: Vec2,
+    pub local_angle: f32,
+}
+
+#[derive(Serialize, Deserialize, Debug)]
+pub struct SawSave {
+    pub position: Vec2,
+    pub body_position: Vec2,
+    pub angle: f32,
+    pub active: bool,
+}
+
+#[derive(Debug, thiserror::Error)]
+pub enum SaveError {
+    #[error("could not write save: {0}")]
+    Serialize(#[from] ron::Error),
+    #[error("could not read save: {0}")]
+    Deserialize(#[from] ron::error::SpannedError),
+    #[cfg(not(target_family = "wasm"))]
+    #[error("could not access save file: {0}")]
+    Io(#[from] std::io::Error),
+    #[error("no place to store saves: {0}")]
+    Storage(String),
+}
+
+#[cfg(not(target_family = "wasm"))]
+fn save_path(name: &str) -> Result<std::path::PathBuf, SaveError> {
+    let dir = dirs::data_dir()
+        .ok_or_else(|| SaveError::Storage("no user data directory".to_string()))?;
+    Ok(dir.join("custom_furniture").join(format!("{name}.ron")))
+}
+
+/// Stores the save called `name`, replacing the one stored before.
+#[cfg(not(target_family = "wasm"))]
+pub fn write_save(name: &str, contents: &str) -> Result<(), SaveError> {
+    let path = save_path(name)?;
+    if let Some(dir) = path.parent() {
+        std::fs::create_dir_all(dir)?;
+    }
+    std::fs::write(path, contents)?;
+    Ok(())
+}
+
+/// The save called `name`, if there is one.
+#[cfg(not(target_family = "wasm"))]
+pub fn read_save(name: &str) -> Result<Option<String>, SaveError> {
+    match std::fs::read_to_string(save_path(name)?) {
+        Ok(contents) => Ok(Some(contents)),
+        Err(e) if e.kind() == std::io::ErrorKind::NotFound => Ok(None),
+        Err(e) => Err(e.into()),
+    }
+}
+
+#[cfg(target_family = "wasm")]
+fn storage_key(name: &str) -> String {
+    format!("custom_furniture.{name}")
+}
+
+#[cfg(target_family = "wasm")]
+fn local_storage() -> Result<web_sys::Storage, SaveError> {
+    web_sys::window()
+        .and_then(|w| w.local_storage().ok().flatten())
+        .ok_or_else(|| SaveError::Storage("local storage is not available".to_string()))
+}
+
+/// Stores the save called `name`, replacing the one stored before.
+#[cfg(target_family = "wasm")]
+pub fn write_save(name: &str, contents: &str) -> Result<(), SaveError> {
+    local_storage()?
+        .set_item(&storage_key(name), contents)
+        .map_err(|e| SaveError::Storage(format!("{e:?}")))
+}
+
+/// The save called `name`, if there is one.
+#[cfg(target_family = "wasm")]
+pub fn read_save(name: &str) -> Result<Option<String>, SaveError> {
+    local_storage()?
+        .get_item(&storage_key(name))
+        .map_err(|e| SaveError::Storage(format!("{e:?}")))
+}
+
+impl WorkshopSave {
+    const NAME: &'static str = "workshop";
+
+    pub fn store(&self) -> Result<(), SaveError> {
+        write_save(Self::NAME, &ron::ser::to_string_pretty(self, ron::ser::PrettyConfig::default())?)
+    }
+
+    /// The stored save, if there is one.
+    pub fn load() -> Result<Option<Self>, SaveError> {
+        match read_save(Self::NAME)? {
+            Some(contents) => Ok(Some(ron::de::from_str(&contents)?)),
+            None => Ok(None),
+        }
+    }
+}
+
+/// The planks, joints and saw of the workshop, for capturing them in a [`WorkshopSave`].
+#[derive(SystemParam)]
+pub struct Workshop<'w, 's> {
+    planks: Query<
+        'w,
+        's,
+        (
+            Entity,
+            &'static PlankShape,
+            &'static Position,
+            &'static Rotation,
+            Option<&'static LinearVelocity>,
+            Option<&'static AngularVelocity>,
+            &'static ColorInfo,
+            Option<&'static Glue>,
+            &'static PlankSpecies,
+            &'static Grain,
+            Option<&'static PlankLineage>,
+        ),
+        With<Plank>,
+    >,
+    joints: Query<'w, 's, (&'static PlankJoint, &'static Children)>,
+    fasteners: Query<'w, 's, (&'static Fastener, &'static DrivenFastener)>,
+    glue_lines: Query<'w, 's, &'static GlueLine>,
+    saw: Query<'w, 's, (&'static Position, &'static Saw), Without<SawBody>>,
+    saw_body: Query<'w, 's, &'static Position, With<SawBody>>,
+}
+
+impl Workshop<'_, '_> {
+    /// The plank `e` as it is now, if it is one.
+    pub fn plank(&self, e: Entity) -> Option<PlankSave> {
+        let (_, shape, position, rotation, l_vel, a_vel, color_info, glue, species, grain, lineage) =
+            self.planks.get(e).ok()?;
+        let color = color_info.0.to_srgba();
+        Some(PlankSave {
+            shape: shape.clone(),
+            position: position.0,
+            rotation: rotation.as_radians(),
+            linear_velocity: l_vel.map_or(Vec2::ZERO, |v| v.0),
+            angular_velocity: a_vel.map_or(0., |v| v.0),
+            color: [color.red, color.green, color.blue, color.alpha],
+            glue: glue.map(|g| g.spots.clone()).unwrap_or_default(),
+            species: species.0.path().map(|p| p.to_string()),
+            grain: grain.0,
+            lineage: lineage.cloned(),
+        })
+    }
+
+    /// The workshop as it is now.
+    pub fn capture(&self) -> WorkshopSave {
+        let mut indices = HashMap::new();
+        let mut save = WorkshopSave {
+            planks: Vec::new(),
+            joints: Vec::new(),
+            saw: None,
+            level: None,
+        };
+        for (e, ..) in &self.planks {
+            indices.insert(e, save.planks.len());
+            save.planks.extend(self.plank(e));
+        }
+        for (joint, children) in &self.joints {
+            let (Some(&first), Some(&other)) = (indices.get(&joint.planks[0]), indices.get(&joint.planks[1]))
+            else {
+                continue;
+            };
+            save.joints.push(JointSave {
+                planks: [first, other],
+                local_anchors: joint.local_anchors,
+                brace_anchors: joint.brace_anchors,
+                fasteners: self
+                    .fasteners
+                    .iter_many(children)
+                    .filter_map(|(fastener, driven)| {
+                        Some(FastenerSave {
+                            kind: fastener.kind,
+                            plank: *indices.get(&driven.plank)?,
+                            local_position: driven.local_position,
+                            local_angle: driven.local_angle,
+                        })
+                    })
+                    .collect(),
+                glue_lines: self.glue_lines.iter_many(children).map(|g| g.length).collect(),
+                glue_anchors: self.glue_lines.iter_many(children).map(|g| g.local_anchors).collect(),
+            });
+        }
+        if let (Ok((position, saw)), Ok(body_position)) = (self.saw.get_single(), self.saw_body.get_single()) {
+            save.saw = Some(SawSave {
+                position: position.0,
+                body_position: body_position.0,
+                angle: saw.angle,
+                active: saw.active,
+            });
+        }
+        save
+    }
+}
+
+fn save_workshop(
+    workshop: Workshop,
+    current: Res<CurrentLevel>,
+    order: Option<Res<ActiveOrder>>,
+    seed: Res<LevelSeed>,
+    stats: Res<BuildStats>,
+    time: Res<Time>,
+) {
+    let level = current.0.path().map(|path| LevelSave {
+        path: path.to_string(),
+        order: order.map(|o| o.path.clone()),
+        seed: seed.0,
+        stats: stats.clone(),
+        build_secs: (time.elapsed() - stats.started).as_secs_f32(),
+    });
+    let save = WorkshopSave {
+        level,
+        ..workshop.capture()
+    };
+    match save.store() {
+        Ok(()) => info!("Saved workshop with {} planks", save.planks.len()),
+        Err(e) => error!("{e}"),
+    }
+}
+
+fn load_workshop(planks: Query<Entity, With<Plank>>, mut commands: Commands) {
+    match WorkshopSave::load() {
+        Ok(Some(save)) => {
+            commands.trigger(BuildAction::new("Load", &planks));
+            // Parked rather than replaced, so undoing the load brings them back.
+            for plank in &planks {
+                commands.add(ParkPlank(plank));
+            }
+            commands.add(RestoreWorkshop(save));
+        }
+        Ok(None) => info!("There is no saved workshop to load"),
+        Err(e) => error!("{e}"),
+    }
+}
+
+impl PlankSave {
+    /// Builds the plank on `e`, which may be a new entity or one the plank used to be.
+    pub fn spawn(self, world: &mut World, e: Entity) {
+        // The lineage goes in first, so the plank isn't taken for new stock.
+        if let Some(lineage) = self.lineage {
+            world.entity_mut(e).insert(lineage);
+        }
+        let [r, g, b, a] = self.color;
+        let species = self.species.as_deref().unwrap_or(WoodAssets::PATH_DEFAULT);
+        let species = world.resource::<AssetServer>().load(species.to_string());
+        world.trigger_targets(
+            SpawnPlank {
+                shape: self.shape,
+                position: self.position,
+                rotation: Rotation::from_radians(self.rotation),
+                l_vel: Some(LinearVelocity(self.linear_velocity)),
+                a_vel: Some(AngularVelocity(self.angular_velocity)),
+                color: Color::srgba(r, g, b, a),
+                species,
+                grain: Grain(self.grain),
+                joints: Vec::new(),
+                origin: None,
+            },
+            e,
+        );
+        if !self.glue.is_empty() {
+            world.entity_mut(e).insert(Glue {
+                spots: self.glue,
+                ..default()
+            });
+        }
+    }
+}
+
+/// Spawns a joint with its fasteners and cured glue lines.
+pub fn spawn_joint(
+    world: &mut World,
+    joint: PlankJoint,
+    fasteners: impl IntoIterator<Item = (FastenerKind, DrivenFastener)>,
+    glue_lines: impl IntoIterator<Item = GlueLine>,
+) {
+    world.spawn(joint_bundle(joint)).with_children(|children| {
+        for (kind, driven) in fasteners {
+            // Placed on the plank by the joint systems.
+            children.spawn(driven_fastener_bundle(kind, Transform::from_xyz(0., 0., 1.), driven));
+        }
+        for glue_line in glue_lines {
+            children.spawn(glue_line_bundle(glue_line));
+        }
+    });
+}
+
+/// A [`Command`] that replaces the planks, joints and saw state with those from a save. Saves
+/// without a saw leave it as it is.
+///
+/// The save's level isn't restored here, as the level has to be chosen before gameplay starts.
+/// See [`LevelSave::restore`].
+pub struct RestoreWorkshop(pub WorkshopSave);
+
+impl Command for RestoreWorkshop {
+    fn apply(self, world: &mut World) {
+        let WorkshopSave { planks, joints, saw, .. } = self.0;
+        let old: Vec<Entity> = world
+            .query_filtered::<Entity, Or<(With<Plank>, With<PlankJoint>)>>()
+            .iter(world)
+            .collect();
+        for e in old {
+            if let Some(entity) = world.get_entity_mut(e) {
+                entity.despawn_recursive();
+            }
+        }
+
+        let mut counter = world.resource_mut::<LineageCounter>();
+        for lineage in planks.iter().filter_map(|p| p.lineage.as_ref()) {
+            counter.skip_past(lineage);
+        }
+
+        let entities: Vec<Entity> = planks
+            .into_iter()
+            .map(|plank| {
+                let e = world.spawn_empty().id();
+                plank.spawn(world, e);
+                e
+            })
+            .collect();
+
+        for joint in joints {
+            let (Some(&first), Some(&other)) = (entities.get(joint.planks[0]), entities.get(joint.planks[1]))
+            else {
+                warn!("Skipping a saved joint between planks that don't exist");
+                continue;
+            };
+            let fasteners = joint.fasteners.into_iter().filter_map(|fastener| {
+                let plank = *entities.get(fastener.plank)?;
+                Some((
+                    fastener.kind,
+                    DrivenFastener {
+                        plank,
+                        local_position: fastener.local_position,
+                        local_angle: fastener.local_angle,
+                    },
+                ))
+            });
+            let glue_anchors = joint.glue_anchors.into_iter().chain(std::iter::repeat(joint.local_anchors));
+            let glue_lines = joint
+                .glue_lines
+                .into_iter()
+                .zip(glue_anchors)
+                .map(|(length, local_anchors)| GlueLine { length, local_anchors });
+            spawn_joint(
+                world,
+                PlankJoint {
+                    planks: [first, other],
+                    local_anchors: joint.local_anchors,
+                    brace_anchors: joint.brace_anchors,
+                    compliance: 0.,
+                },
+                fasteners,
+                glue_lines,
+            );
+        }
+
+        if let Some(saved) = saw {
+            let mut saws = world.query_filtered::<(&mut Position, &mut Rotation, &mut Saw), Without<SawBody>>();
+            if let Ok((mut position, mut rotation, mut saw)) = saws.get_single_mut(world) {
+                position.0 = saved.position;
+                *rotation = Rotation::from_degrees(-saved.angle);
+                saw.angle = saved.angle;
+                saw.active = saved.active;
+            }
+            let mut bodies = world.query_filtered::<&mut Position, With<SawBody>>();
+            if let Ok(mut position) = bodies.get_single_mut(world) {
+                position.0 = saved.body_position;
+            }
+        }
+    }
+}
+
+impl LevelSave {
+    /// Makes the saved level the one gameplay spawns, with its order and seed. Levels that
+    /// aren't loaded are skipped, leaving the current level.
+    pub fn restore(&self, world: &mut World) {
+        let level = world.resource::<AssetServer>().load::<LevelDef>(self.path.clone());
+        if !world.resource::<Assets<LevelDef>>().contains(&level) {
+            warn!("Level {} is not loaded, continuing in the current level", self.path);
+            return;
+        }
+        world.insert_resource(CurrentLevel(level));
+        match &self.order {
+            Some(path) => world.insert_resource(ActiveOrder { path: path.clone() }),
+            None => {
+                world.remove_resource::<ActiveOrder>();
+            }
+        }
+        world.insert_resource(LevelSeed(self.seed));
+    }
+}
+
+/// A save to restore once the gameplay screen has spawned its level.
+#[derive(Resource)]
+pub struct PendingRestore(pub WorkshopSave);
+
+/// Restores the [`PendingRestore`], if there is one, along with its build stats. Runs after the
+/// level is spawned and the stats are reset.
+pub fn restore_pending(mut commands: Commands) {
+    commands.add(|world: &mut World| {
+        let Some(PendingRestore(mut save)) = world.remove_resource::<PendingRestore>() else {
+            return;
+        };
+        if let Some(level) = save.level.take() {
+            let now = world.resource::<Time>().elapsed();
+            world.insert_resource(BuildStats {
+                started: now.saturating_sub(Duration::from_secs_f32(level.build_secs)),
+                ..level.stats
+            });
+        }
+        RestoreWorkshop(save).apply(world);
+    });
+}
@@ -0,0 +1,224 @@
+//! Scoring a build when the player submits it.
+//!
+//! Cuts and fasteners are counted in [`BuildStats`] while building. Submitting with `Enter` or
+//! the "Submit" button turns them into a [`BuildResult`] and shows the results screen.
+
+use crate::demo::blueprint::{current_blueprint, main_build, Blueprint, BlueprintProgress};
+use crate::demo::export::CutList;
+use crate::demo::joint::PlankJoint;
+use crate::demo::level::{CurrentLevel, LevelDef, OrderDef, Plank};
+use crate::demo::lineage::{PlankCut, PlankLineage};
+use crate::demo::load_test::{LoadTest, LoadTestOutcome};
+use crate::demo::save::restore_pending;
+use crate::demo::shape::PlankShape;
+use crate::demo::wood::{PlankSpecies, WoodSpecies};
+use crate::screens::Screen;
+use crate::theme::prelude::*;
+use avian2d::position::{Position, Rotation};
+use bevy::input::common_conditions::input_just_pressed;
+use bevy::prelude::*;
+use bevy::ui::Val::Px;
+use bevy::utils::HashSet;
+use serde::{Deserialize, Serialize};
+use std::time::Duration;
+
+pub(super) fn plugin(app: &mut App) {
+    app.init_resource::<BuildStats>();
+    app.add_systems(OnEnter(Screen::Gameplay), (reset_stats.before(restore_pending), spawn_submit_button));
+    app.add_systems(
+        Update,
+        (|mut commands: Commands| commands.trigger(SubmitBuild))
+            .run_if(in_state(Screen::Gameplay).and_then(input_just_pressed(KeyCode::Enter))),
+    );
+    app.observe(submit_build);
+    app.observe(count_cut);
+}
+
+/// Counters collected while building.
+#[derive(Resource, Clone, Default, Debug, Serialize, Deserialize)]
+pub struct BuildStats {
+    /// When building started, as [`Time::elapsed`]. Saves keep the time spent instead.
+    #[serde(skip)]
+    pub started: Duration,
+    pub cuts: usize,
+    /// Area of wood turned to sawdust by cuts.
+    pub sawdust: f32,
+    pub fasteners: usize,
+    /// Money spent on fasteners.
+    pub spent: u32,
+}
+
+/// Counts every cut, once it is made.
+fn count_cut(trigger: Trigger<PlankCut>, mut stats: ResMut<BuildStats>) {
+    stats.cuts += 1;
+    stats.sawdust += trigger.event().sawdust;
+}
+
+fn reset_stats(mut commands: Commands, time: Res<Time>) {
+    commands.insert_resource(BuildStats {
+        started: time.elapsed(),
+        ..default()
+    });
+}
+
+/// Ends the session and scores the build.
+#[derive(Event)]
+pub struct SubmitBuild;
+
+/// How a submitted build did.
+#[derive(Resource, Clone, Debug)]
+pub struct BuildResult {
+    /// Name of the blueprint the build was scored against.
+    pub blueprint: Option<String>,
+    /// Completion of the blueprint, between 0 and 1.
+    pub completion: f32,
+    /// Area of the planks in the build.
+    pub material_used: f32,
+    /// Area of the offcuts and sawdust. Stock planks left whole don't count.
+    pub material_wasted: f32,
+    pub cuts: usize,
+    /// Average difference between the build's planks and the blueprint's parts.
+    pub cut_error: Option<f32>,
+    pub fasteners: usize,
+    /// Outcome of the last load test, if one was finished.
+    pub test: Option<LoadTestOutcome>,
+    pub time: Duration,
+    /// Height of the build's bounding box.
+    pub height: f32,
+    pub spent: u32,
+    /// The order the level was, if any.
+    pub order: Option<OrderDef>,
+    /// Whether the build meets every requirement of the order.
+    pub fulfilled: Option<bool>,
+    /// How to cut the build's planks from stock.
+    pub cut_list: CutList,
+}
+
+/// Completion needed for the blueprint's star.
+const COMPLETION_STAR: f32 = 0.9;
+/// Average cut error allowed for the craftsmanship star.
+const PRECISION_STAR: f32 = 1.;
+
+impl BuildResult {
+    /// Up to three stars: one for following the blueprint, one for passing the load test and one
+    /// for precise cuts without wasting more wood than was used.
+    pub fn stars(&self) -> usize {
+        let followed = self.completion >= COMPLETION_STAR;
+        let passed = self.test == Some(LoadTestOutcome::Passed);
+        let precise = self.cut_error.is_some_and(|e| e <= PRECISION_STAR)
+            && self.material_wasted <= self.material_used;
+        [followed, passed, precise].into_iter().filter(|s| *s).count()
+    }
+
+    fn meets(&self, order: &OrderDef) -> bool {
+        let tall_enough = order.min_height.map_or(true, |h| self.height >= h);
+        let held = !order.load_bearing || self.test == Some(LoadTestOutcome::Passed);
+        let followed = self.blueprint.is_none() || self.completion >= COMPLETION_STAR;
+        tall_enough && held && followed && self.spent <= order.budget
+    }
+}
+
+fn press_submit(_trigger: Trigger<OnPress>, mut commands: Commands) {
+    commands.trigger(SubmitBuild);
+}
+
+fn spawn_submit_button(mut commands: Commands) {
+    commands
+        .spawn((
+            Name::new("Submit"),
+            StateScoped(Screen::Gameplay),
+            NodeBundle {
+                style: Style {
+                    position_type: PositionType::Absolute,
+                    left: Px(10.0),
+                    bottom: Px(40.0),
+                    ..default()
+                },
+                ..default()
+            },
+        ))
+        .with_children(|children| {
+            children.button("Submit").observe(press_submit);
+        });
+}
+
+fn submit_build(
+    _trigger: Trigger<SubmitBuild>,
+    current: Res<CurrentLevel>,
+    levels: Res<Assets<LevelDef>>,
+    blueprints: Res<Assets<Blueprint>>,
+    progress: Res<BlueprintProgress>,
+    stats: Res<BuildStats>,
+    test: Option<Res<LoadTest>>,
+    time: Res<Time>,
+    planks: Query<
+        (
+            Entity,
+            &PlankShape,
+            &Position,
+            &Rotation,
+            Option<&PlankLineage>,
+            Option<&PlankSpecies>,
+        ),
+        With<Plank>,
+    >,
+    joints: Query<&PlankJoint>,
+    species: Res<Assets<WoodSpecies>>,
+    mut next_screen: ResMut<NextState<Screen>>,
+    mut commands: Commands,
+) {
+    let blueprint = current_blueprint(&current, &levels, &blueprints);
+    let build = main_build(&progress, planks.iter().map(|(e, ..)| e), joints.iter().map(|j| j.planks));
+
+    // Stock planks that were cut or built with were used, so whatever is left of them is waste.
+    let used_stock: HashSet<usize> = planks
+        .iter()
+        .filter_map(|(e, .., lineage, _)| {
+            let lineage = lineage?;
+            (build.contains(&e) || !lineage.cuts.is_empty()).then_some(lineage.stock)
+        })
+        .collect();
+
+    let mut used = 0.;
+    let mut offcuts = 0.;
+    let mut errors = Vec::new();
+    let (mut bottom, mut top) = (f32::INFINITY, f32::NEG_INFINITY);
+    for (e, shape, position, rotation, lineage, _) in &planks {
+        if build.contains(&e) {
+            used += shape.area();
+            errors.extend(blueprint.and_then(|b| b.part_error(shape.size())));
+            for v in shape.parts().iter().flatten() {
+                let y = (position.0 + *rotation * *v).y;
+                bottom = bottom.min(y);
+                top = top.max(y);
+            }
+        } else if lineage.map_or(true, |l| used_stock.contains(&l.stock)) {
+            offcuts += shape.area();
+        }
+    }
+    let cut_list = CutList::new(planks.iter().filter(|(e, ..)| build.contains(e)).filter_map(
+        |(_, shape, .., lineage, plank_species)| {
+            Some((shape, lineage?, plank_species.and_then(|s| species.get(&s.0))))
+        },
+    ));
+
+    let mut result = BuildResult {
+        blueprint: blueprint.map(|b| b.name.clone()),
+        completion: progress.completion(),
+        material_used: used,
+        material_wasted: offcuts + stats.sawdust,
+        cuts: stats.cuts,
+        cut_error: (!errors.is_empty()).then(|| errors.iter().sum::<f32>() / errors.len() as f32),
+        fasteners: stats.fasteners,
+        test: test.and_then(|t| t.outcome),
+        time: time.elapsed().saturating_sub(stats.started),
+        height: (top - bottom).max(0.),
+        spent: stats.spent,
+        order: levels.get(&current.0).and_then(|l| l.order.clone()),
+        fulfilled: None,
+        cut_list,
+    };
+    result.fulfilled = result.order.as_ref().map(|order| result.meets(order));
+    commands.insert_resource(result);
+    next_screen.set(Screen::Results);
+}
@@ -0,0 +1,85 @@
+//! Seeded randomness, so the same seed always deals the same plank pile.
+//!
+//! The seed can be passed on the command line as `--seed <number>`, or rerolled from the title
+//! screen. All gameplay randomness should draw from [`GameRng`] rather than `thread_rng`.
+
+use crate::screens::Screen;
+use crate::theme::prelude::*;
+use bevy::prelude::*;
+use bevy::ui::Val::*;
+use rand::rngs::StdRng;
+use rand::SeedableRng;
+
+pub(super) fn plugin(app: &mut App) {
+    let seed = seed_from_args().unwrap_or_else(random_seed);
+    app.insert_resource(LevelSeed(seed));
+    app.insert_resource(GameRng::new(seed));
+    app.add_systems(OnEnter(Screen::Gameplay), spawn_seed_label);
+    app.add_systems(Update, update_seed_labels.run_if(resource_changed::<LevelSeed>));
+}
+
+/// The seed the level is generated from.
+#[derive(Resource, Clone, Copy, Debug, PartialEq, Eq)]
+pub struct LevelSeed(pub u64);
+
+/// The random number generator for everything random in gameplay.
+/// It is reseeded from [`LevelSeed`] whenever a level is spawned.
+#[derive(Resource)]
+pub struct GameRng(pub StdRng);
+
+impl GameRng {
+    pub fn new(seed: u64) -> Self {
+        Self(StdRng::seed_from_u64(seed))
+    }
+}
+
+/// Text showing the current [`LevelSeed`].
+#[derive(Component)]
+pub struct SeedLabel;
+
+/// Picks a new seed. Kept to 32 bits so it's easy to read out and type back in.
+pub fn random_seed() -> u64 {
+    rand::random::<u32>().into()
+}
+
+/// Reads the seed from a `--seed <number>` or `--seed=<number>` argument.
+fn seed_from_args() -> Option<u64> {
+    let mut args = std::env::args().skip(1);
+    while let Some(arg) = args.next() {
+        let value = match arg.strip_prefix("--seed") {
+            Some("") => args.next(),
+            Some(value) => value.strip_prefix('=').map(str::to_owned),
+            None => continue,
+        };
+        match value.as_deref().map(str::parse) {
+            Some(Ok(seed)) => return Some(seed),
+            _ => warn!("Ignoring invalid seed argument, expected `--seed <number>`"),
+        }
+    }
+    None
+}
+
+pub fn seed_text(seed: LevelSeed) -> String {
+    format!("Seed {}", seed.0)
+}
+
+fn spawn_seed_label(mut commands: Commands, seed: Res<LevelSeed>) {
+    commands
+        .label(seed_text(*seed))
+        .insert((
+            SeedLabel,
+            StateScoped(Screen::Gameplay),
+            Style {
+                position_type: PositionType::Absolute,
+                right: Px(10.0),
+                bottom: Px(10.0),
+                ..default()
+            },
+        ));
+}
+
+fn update_seed_labels(seed: Res<LevelSeed>, mut labels: Query<&mut Text, With<SeedLabel>>) {
+    for mut text in &mut labels {
+        text.sections[0].value = seed_text(*seed);
+    }
+}
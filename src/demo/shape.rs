@@ -0,0 +1,517 @@
+//! Plank outlines, and the geometry needed to cut and drill them.
+
+use avian2d::prelude::{Collider, Rotation};
+use bevy::{
+    prelude::*,
+    render::{
+        mesh::{Indices, PrimitiveTopology},
+        render_asset::RenderAssetUsages,
+    },
+};
+use serde::{Deserialize, Serialize};
+
+/// Pieces with a smaller area than this are sawdust and don't become planks.
+const MIN_PIECE_AREA: f32 = 1.;
+/// Convex parts with a smaller area than this are slivers left over from clipping.
+const MIN_PART_AREA: f32 = 0.01;
+/// Number of sides of the polygon that approximates a drilled hole.
+const HOLE_SEGMENTS: usize = 12;
+/// Convex parts closer than this to each other belong to the same piece.
+const TOUCH_DISTANCE: f32 = 0.05;
+/// How far the ends of a curved cut are carried on, so that the cut goes all the way through.
+const CUT_RUN_OUT: f32 = 1e4;
+/// Length covered by one repeat of a texture on a plank's mesh.
+const TEXTURE_SPAN: f32 = 40.;
+
+/// The outline of a plank in the plank's local frame.
+///
+/// The shape is made of convex parts, each stored counter-clockwise, so that planks with holes
+/// can still be built from convex colliders. New and freshly cut shapes are centered on their
+/// centroid, so the entity's [`Position`](avian2d::prelude::Position) starts out as its center
+/// of mass.
+#[derive(Component, Clone, Debug, Serialize, Deserialize)]
+pub struct PlankShape {
+    parts: Vec<Vec<Vec2>>,
+    holes: Vec<Hole>,
+}
+
+/// A round hole bored through a plank, in the plank's local frame.
+#[derive(Clone, Copy, Debug, Serialize, Deserialize)]
+pub struct Hole {
+    pub center: Vec2,
+    pub radius: f32,
+}
+
+impl PlankShape {
+    /// An axis-aligned rectangle, the shape of fresh stock.
+    pub fn rectangle(width: f32, height: f32) -> Self {
+        let half = Vec2::new(width, height) / 2.;
+        Self {
+            parts: vec![vec![
+                Vec2::new(-half.x, -half.y),
+                Vec2::new(half.x, -half.y),
+                Vec2::new(half.x, half.y),
+                Vec2::new(-half.x, half.y),
+            ]],
+            holes: Vec::new(),
+        }
+    }
+
+    pub fn parts(&self) -> &[Vec<Vec2>] {
+        &self.parts
+    }
+
+    pub fn holes(&self) -> &[Hole] {
+        &self.holes
+    }
+
+    /// Whether there is a hole at `point`, in the local frame.
+    pub fn has_hole_at(&self, point: Vec2) -> bool {
+        self.holes.iter().any(|h| h.center.distance(point) <= h.radius)
+    }
+
+    /// Size of the shape's bounding box in its local frame.
+    pub fn size(&self) -> Vec2 {
+        let (min, max) = self.bounds();
+        max - min
+    }
+
+    pub fn area(&self) -> f32 {
+        self.parts.iter().map(|p| polygon_area(p)).sum()
+    }
+
+    /// Area lost when cutting this shape into `pieces`.
+    pub fn sawdust<'a>(&self, pieces: impl IntoIterator<Item = &'a PlankShape>) -> f32 {
+        let left: f32 = pieces.into_iter().map(PlankShape::area).sum();
+        (self.area() - left).max(0.)
+    }
+
+    /// The area where this shape and `other` overlap, given where each of them is in the world.
+    pub fn overlap_area(
+        &self,
+        position: Vec2,
+        rotation: Rotation,
+        other: &PlankShape,
+        other_position: Vec2,
+        other_rotation: Rotation,
+    ) -> f32 {
+        let other_parts: Vec<Vec<Vec2>> = other
+            .parts
+            .iter()
+            .map(|p| world_vertices(p, other_position, other_rotation))
+            .collect();
+        let mut area = 0.;
+        for part in &self.parts {
+            let part = world_vertices(part, position, rotation);
+            for other in &other_parts {
+                area += convex_intersection_area(&part, other);
+            }
+        }
+        area
+    }
+
+    /// The edges of the outline, including around holes, in the local frame.
+    ///
+    /// Edges shared between two convex parts are inside the plank, so they are skipped.
+    pub fn outline(&self) -> impl Iterator<Item = (Vec2, Vec2)> + '_ {
+        self.parts
+            .iter()
+            .flat_map(|part| {
+                let n = part.len();
+                (0..n).map(move |i| (part[i], part[(i + 1) % n]))
+            })
+            .filter(|&(a, b)| {
+                // The outside of each counter-clockwise edge is to its right.
+                let outside = (a + b) / 2. - (b - a).perp().normalize_or_zero() * 0.01;
+                !self.contains(outside)
+            })
+    }
+
+    /// The point on the outline closest to `point`, both in the local frame.
+    pub fn closest_boundary_point(&self, point: Vec2) -> Vec2 {
+        self.outline()
+            .map(|(a, b)| closest_point_on_segment(point, a, b))
+            .min_by(|a, b| a.distance_squared(point).total_cmp(&b.distance_squared(point)))
+            .unwrap_or(point)
+    }
+
+    /// Whether `point`, in the local frame, lies inside the plank.
+    pub fn contains(&self, point: Vec2) -> bool {
+        self.parts.iter().any(|part| convex_contains(part, point))
+    }
+
+    /// Where `path`, in the local frame, first enters the plank and where it last leaves it, or
+    /// `None` if it never touches the plank.
+    ///
+    /// Each segment is tested against the outline, so a path crossing a thin plank between two of
+    /// its points still counts. A path starting or ending inside enters or leaves there.
+    pub fn path_span(&self, path: &[Vec2]) -> Option<(Vec2, Vec2)> {
+        let mut points = Vec::new();
+        for w in path.windows(2) {
+            if self.contains(w[0]) {
+                points.push(w[0]);
+            }
+            let mut crossings: Vec<f32> = self
+                .outline()
+                .filter_map(|(a, b)| segment_intersection(w[0], w[1], a, b))
+                .collect();
+            crossings.sort_by(f32::total_cmp);
+            points.extend(crossings.into_iter().map(|t| w[0].lerp(w[1], t)));
+        }
+        if let Some(&last) = path.last().filter(|v| self.contains(**v)) {
+            points.push(last);
+        }
+        Some((*points.first()?, *points.last()?))
+    }
+
+    /// The collider matching this outline, or `None` if the shape is degenerate.
+    pub fn collider(&self) -> Option<Collider> {
+        let mut colliders: Vec<Collider> = self
+            .parts
+            .iter()
+            .filter_map(|p| Collider::convex_hull(p.clone()))
+            .collect();
+        match colliders.len() {
+            0 => None,
+            1 => colliders.pop(),
+            _ => Some(Collider::compound(
+                colliders.into_iter().map(|c| (Vec2::ZERO, 0., c)).collect(),
+            )),
+        }
+    }
+
+    /// A flat mesh of this outline. The texture's x axis runs along `grain`, a direction in the
+    /// local frame, and repeats every [`TEXTURE_SPAN`] units.
+    pub fn mesh(&self, grain: Vec2) -> Mesh {
+        let grain = grain.try_normalize().unwrap_or(Vec2::X);
+        let mut positions: Vec<[f32; 3]> = Vec::new();
+        let mut uvs: Vec<[f32; 2]> = Vec::new();
+        let mut indices: Vec<u32> = Vec::new();
+        for part in &self.parts {
+            let first = positions.len() as u32;
+            for v in part {
+                let uv = Vec2::new(v.dot(grain), v.dot(grain.perp())) / TEXTURE_SPAN;
+                positions.push([v.x, v.y, 0.]);
+                uvs.push([uv.x, 1. - uv.y]);
+            }
+            // Convex parts can be triangulated as a fan around their first vertex.
+            for i in 1..part.len().saturating_sub(1) as u32 {
+                indices.extend([first, first + i, first + i + 1]);
+            }
+        }
+        let normals = vec![[0., 0., 1.]; positions.len()];
+        Mesh::new(PrimitiveTopology::TriangleList, RenderAssetUsages::default())
+            .with_inserted_attribute(Mesh::ATTRIBUTE_POSITION, positions)
+            .with_inserted_attribute(Mesh::ATTRIBUTE_NORMAL, normals)
+            .with_inserted_attribute(Mesh::ATTRIBUTE_UV_0, uvs)
+            .with_inserted_indices(Indices::U32(indices))
+    }
+
+    /// Cuts the shape along the line through `point` with the given `normal`, removing a strip
+    /// `kerf` wide on either side of the line.
+    ///
+    /// Returns the pieces behind the line (opposite to `normal`), then those in front of it. A
+    /// side of a concave shape can fall apart into several pieces that don't touch, which are
+    /// returned separately. Each piece is re-centered on its own centroid and comes with the
+    /// offset of that centroid from the center of the original shape. Pieces too small to keep
+    /// are left out.
+    pub fn split(&self, point: Vec2, normal: Vec2, kerf: f32) -> Vec<(PlankShape, Vec2)> {
+        let normal = normal.normalize_or_zero();
+        if normal == Vec2::ZERO {
+            return Vec::new();
+        }
+        [-normal, normal]
+            .into_iter()
+            .flat_map(|n| {
+                let parts = self
+                    .parts
+                    .iter()
+                    .map(|p| clip_half_plane(p, point + n * kerf, n))
+                    .filter(|p| p.len() >= 3 && polygon_area(p) >= MIN_PART_AREA)
+                    .collect();
+                self.pieces(parts)
+            })
+            .collect()
+    }
+
+    /// Cuts the shape along the polyline `path`, removing a strip `kerf` wide on either side.
+    /// The cut carries straight on past both ends of the path, so it always goes all the way
+    /// through.
+    ///
+    /// Each bend of the path bounds a slab in which the cut is straight, so every convex part is
+    /// clipped slab by slab. The resulting parts are grouped into pieces by which of them touch,
+    /// as a winding cut can leave more than two pieces. Like [`PlankShape::split`], each piece is
+    /// re-centered and comes with the offset of its centroid.
+    pub fn cut_along(&self, path: &[Vec2], kerf: f32) -> Vec<(PlankShape, Vec2)> {
+        let mut path: Vec<Vec2> = path.iter().copied().filter(|v| v.is_finite()).collect();
+        // Points on top of the one before give no direction to cut in.
+        path.dedup_by(|a, b| (*a - *b).normalize_or_zero() == Vec2::ZERO);
+        if path.len() < 2 {
+            return Vec::new();
+        }
+        let start_dir = (path[1] - path[0]).normalize_or_zero();
+        let end_dir = (path[path.len() - 1] - path[path.len() - 2]).normalize_or_zero();
+        path.insert(0, path[0] - start_dir * CUT_RUN_OUT);
+        path.push(path[path.len() - 1] + end_dir * CUT_RUN_OUT);
+
+        let dirs: Vec<Vec2> = path.windows(2).map(|w| (w[1] - w[0]).normalize_or_zero()).collect();
+        // Slab boundaries run across the path at each inner vertex, halfway between the
+        // directions of the segments meeting there. Where the path turns straight back there is
+        // no halfway, and the zero normal leaves the slab unclipped.
+        let boundaries: Vec<(Vec2, Vec2)> = (1..dirs.len())
+            .map(|i| (path[i], (dirs[i - 1] + dirs[i]).normalize_or_zero()))
+            .collect();
+
+        let mut parts = Vec::new();
+        for part in &self.parts {
+            for (i, dir) in dirs.iter().enumerate() {
+                let mut slab = part.clone();
+                if i > 0 {
+                    let (point, normal) = boundaries[i - 1];
+                    slab = clip_half_plane(&slab, point, normal);
+                }
+                if let Some(&(point, normal)) = boundaries.get(i) {
+                    slab = clip_half_plane(&slab, point, -normal);
+                }
+                if slab.len() < 3 {
+                    continue;
+                }
+                let normal = dir.perp();
+                for n in [normal, -normal] {
+                    let side = clip_half_plane(&slab, path[i] + n * kerf, n);
+                    if side.len() >= 3 && polygon_area(&side) >= MIN_PART_AREA {
+                        parts.push(side);
+                    }
+                }
+            }
+        }
+
+        self.pieces(parts)
+    }
+
+    /// Groups the parts left of this shape after a cut into pieces by which of them touch, and
+    /// gives each piece the holes along its edges.
+    fn pieces(&self, parts: Vec<Vec<Vec2>>) -> Vec<(PlankShape, Vec2)> {
+        group_touching(parts)
+            .into_iter()
+            .filter_map(|group| {
+                let holes = self
+                    .holes
+                    .iter()
+                    .filter(|h| {
+                        group.iter().any(|p| {
+                            let n = p.len();
+                            (0..n).any(|i| {
+                                closest_point_on_segment(h.center, p[i], p[(i + 1) % n])
+                                    .distance(h.center)
+                                    <= h.radius + TOUCH_DISTANCE
+                            })
+                        })
+                    })
+                    .copied()
+                    .collect();
+                Self::recentered(group, holes)
+            })
+            .collect()
+    }
+
+    /// Bores a round hole through the plank.
+    pub fn drill(&mut self, center: Vec2, radius: f32) {
+        let hole: Vec<Vec2> = (0..HOLE_SEGMENTS)
+            .map(|i| center + Vec2::from_angle(i as f32 / HOLE_SEGMENTS as f32 * std::f32::consts::TAU) * radius)
+            .collect();
+        self.parts = self
+            .parts
+            .iter()
+            .flat_map(|part| subtract_convex(part, center, radius, &hole))
+            .collect();
+        self.holes.push(Hole { center, radius });
+    }
+
+    fn recentered(parts: Vec<Vec<Vec2>>, holes: Vec<Hole>) -> Option<(PlankShape, Vec2)> {
+        let parts: Vec<Vec<Vec2>> = parts
+            .into_iter()
+            .filter(|p| p.len() >= 3 && polygon_area(p) >= MIN_PART_AREA)
+            .collect();
+        let area: f32 = parts.iter().map(|p| polygon_area(p)).sum();
+        if area < MIN_PIECE_AREA {
+            return None;
+        }
+        let centroid = parts
+            .iter()
+            .map(|p| polygon_centroid(p) * polygon_area(p))
+            .sum::<Vec2>()
+            / area;
+        let parts = parts
+            .into_iter()
+            .map(|p| p.into_iter().map(|v| v - centroid).collect())
+            .collect();
+        let holes = holes
+            .into_iter()
+            .map(|h| Hole {
+                center: h.center - centroid,
+                ..h
+            })
+            .collect();
+        Some((PlankShape { parts, holes }, centroid))
+    }
+
+    fn bounds(&self) -> (Vec2, Vec2) {
+        self.parts.iter().flatten().fold(
+            (Vec2::splat(f32::INFINITY), Vec2::splat(f32::NEG_INFINITY)),
+            |(min, max), v| (min.min(*v), max.max(*v)),
+        )
+    }
+}
+
+fn world_vertices(vertices: &[Vec2], position: Vec2, rotation: Rotation) -> Vec<Vec2> {
+    vertices.iter().map(|v| position + rotation * *v).collect()
+}
+
+fn closest_point_on_segment(point: Vec2, a: Vec2, b: Vec2) -> Vec2 {
+    let t = ((point - a).dot(b - a) / (b - a).length_squared().max(f32::EPSILON)).clamp(0., 1.);
+    a.lerp(b, t)
+}
+
+/// How far along `a`-`b` it crosses `c`-`d`, from 0 to 1, if it does.
+fn segment_intersection(a: Vec2, b: Vec2, c: Vec2, d: Vec2) -> Option<f32> {
+    let (r, s) = (b - a, d - c);
+    let denom = r.perp_dot(s);
+    if denom.abs() < f32::EPSILON {
+        return None;
+    }
+    let t = (c - a).perp_dot(s) / denom;
+    let u = (c - a).perp_dot(r) / denom;
+    ((0. ..=1.).contains(&t) && (0. ..=1.).contains(&u)).then_some(t)
+}
+
+fn convex_contains(vertices: &[Vec2], point: Vec2) -> bool {
+    let n = vertices.len();
+    (0..n).all(|i| {
+        let (a, b) = (vertices[i], vertices[(i + 1) % n]);
+        (b - a).perp_dot(point - a) >= 0.
+    })
+}
+
+/// Groups convex parts into the connected pieces they form.
+fn group_touching(parts: Vec<Vec<Vec2>>) -> Vec<Vec<Vec<Vec2>>> {
+    let mut group_of: Vec<usize> = (0..parts.len()).collect();
+    fn root(group_of: &mut [usize], mut i: usize) -> usize {
+        while group_of[i] != i {
+            group_of[i] = group_of[group_of[i]];
+            i = group_of[i];
+        }
+        i
+    }
+    for i in 0..parts.len() {
+        for j in i + 1..parts.len() {
+            if convex_distance(&parts[i], &parts[j]) <= TOUCH_DISTANCE {
+                let (a, b) = (root(&mut group_of, i), root(&mut group_of, j));
+                group_of[a] = b;
+            }
+        }
+    }
+    let mut groups: Vec<(usize, Vec<Vec<Vec2>>)> = Vec::new();
+    for (i, part) in parts.into_iter().enumerate() {
+        let r = root(&mut group_of, i);
+        match groups.iter_mut().find(|(g, _)| *g == r) {
+            Some((_, group)) => group.push(part),
+            None => groups.push((r, vec![part])),
+        }
+    }
+    groups.into_iter().map(|(_, group)| group).collect()
+}
+
+/// Distance between two convex polygons, zero if they overlap.
+fn convex_distance(a: &[Vec2], b: &[Vec2]) -> f32 {
+    if a.iter().any(|v| convex_contains(b, *v)) || b.iter().any(|v| convex_contains(a, *v)) {
+        return 0.;
+    }
+    let vertex_to_edges = |vertices: &[Vec2], polygon: &[Vec2]| {
+        let n = polygon.len();
+        vertices
+            .iter()
+            .flat_map(|v| {
+                (0..n).map(move |i| {
+                    closest_point_on_segment(*v, polygon[i], polygon[(i + 1) % n]).distance(*v)
+                })
+            })
+            .fold(f32::INFINITY, f32::min)
+    };
+    vertex_to_edges(a, b).min(vertex_to_edges(b, a))
+}
+
+fn convex_intersection_area(a: &[Vec2], b: &[Vec2]) -> f32 {
+    let mut overlap = a.to_vec();
+    for (i, &start) in b.iter().enumerate() {
+        if overlap.len() < 3 {
+            return 0.;
+        }
+        let end = b[(i + 1) % b.len()];
+        // Vertices are counter-clockwise, so the inside of each edge is to its left.
+        overlap = clip_half_plane(&overlap, start, (end - start).perp());
+    }
+    polygon_area(&overlap).max(0.)
+}
+
+/// Removes a convex `hole` (approximating a circle) from a convex `part`, returning the convex
+/// parts that are left.
+///
+/// The rest of the part is split into one wedge per edge of the hole: the area seen from the
+/// hole's center between the edge's two corners, beyond the edge. Each wedge is convex.
+fn subtract_convex(part: &[Vec2], center: Vec2, radius: f32, hole: &[Vec2]) -> Vec<Vec<Vec2>> {
+    let touches = convex_contains(part, center) || {
+        let n = part.len();
+        (0..n).any(|i| closest_point_on_segment(center, part[i], part[(i + 1) % n]).distance(center) < radius)
+    };
+    if !touches {
+        return vec![part.to_vec()];
+    }
+    let n = hole.len();
+    (0..n)
+        .map(|i| {
+            let (a, b) = (hole[i], hole[(i + 1) % n]);
+            let wedge = clip_half_plane(part, center, (a - center).perp());
+            let wedge = clip_half_plane(&wedge, center, -(b - center).perp());
+            clip_half_plane(&wedge, a, -(b - a).perp())
+        })
+        .filter(|p| p.len() >= 3 && polygon_area(p) >= MIN_PART_AREA)
+        .collect()
+}
+
+/// Keeps the part of a convex polygon where `(v - point).dot(normal) >= 0`
+/// (Sutherland-Hodgman against a single edge).
+fn clip_half_plane(vertices: &[Vec2], point: Vec2, normal: Vec2) -> Vec<Vec2> {
+    let mut result = Vec::with_capacity(vertices.len() + 1);
+    let distance = |v: Vec2| (v - point).dot(normal);
+    for (i, &current) in vertices.iter().enumerate() {
+        let next = vertices[(i + 1) % vertices.len()];
+        let (d_current, d_next) = (distance(current), distance(next));
+        if d_current >= 0. {
+            result.push(current);
+        }
+        if (d_current >= 0.) != (d_next >= 0.) {
+            let t = d_current / (d_current - d_next);
+            result.push(current.lerp(next, t));
+        }
+    }
+    result
+}
+
+fn polygon_area(vertices: &[Vec2]) -> f32 {
+    let n = vertices.len();
+    (0..n)
+        .map(|i| vertices[i].perp_dot(vertices[(i + 1) % n]))
+        .sum::<f32>()
+        / 2.
+}
+
+fn polygon_centroid(vertices: &[Vec2]) -> Vec2 {
+    let n = vertices.len();
+    let area = polygon_area(vertices);
+    let sum = (0..n).fold(Vec2::ZERO, |acc, i| {
+        let (a, b) = (vertices[i], vertices[(i + 1) % n]);
+        acc + (a + b) * a.perp_dot(b)
+    });
+    sum / (6. * area)
+}
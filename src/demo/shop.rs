@@ -0,0 +1,231 @@
+//! The lumber yard, where earnings from orders buy planks, fasteners and tool upgrades.
+//!
+//! Everything bought goes into the [`Stock`]. Bought planks are delivered to the next order
+//! worked on, in place of its random pile. Bought fasteners are used before any are paid for out
+//! of an order's budget, and upgrades last for good. Free play leaves the planks and fasteners
+//! in stock.
+
+use crate::demo::fastener::FastenerKind;
+use crate::demo::level::PlankDef;
+use crate::demo::save::{read_save, write_save, SaveError};
+use crate::demo::wood::WoodSpecies;
+use crate::demo::CUT_SIZE;
+use crate::screens::Screen;
+use bevy::prelude::*;
+use serde::{Deserialize, Serialize};
+use std::collections::BTreeMap;
+
+pub(super) fn plugin(app: &mut App) {
+    app.insert_resource(Stock::load().unwrap_or_else(|e| {
+        error!("{e}");
+        default()
+    }));
+    app.add_systems(OnExit(Screen::Gameplay), store_stock);
+    app.add_systems(OnExit(Screen::Shop), store_stock);
+}
+
+/// What the player owns, kept between sessions.
+#[derive(Resource, Serialize, Deserialize, Default, Debug)]
+pub struct Stock {
+    pub money: u32,
+    /// Planks waiting to be delivered to the next order.
+    pub planks: Vec<StockPlank>,
+    pub fasteners: BTreeMap<FastenerKind, u32>,
+    pub upgrades: Vec<ToolUpgrade>,
+}
+
+#[derive(Serialize, Deserialize, Clone, Debug)]
+pub struct StockPlank {
+    pub size: Vec2,
+    /// Path of the [`WoodSpecies`], relative to the assets folder.
+    pub species: String,
+}
+
+/// Improvements to the tools, bought once.
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Serialize, Deserialize)]
+pub enum ToolUpgrade {
+    /// Halves the wood lost to every cut.
+    ThinBlade,
+    /// Lets the saw blade tilt in single degrees.
+    BevelGauge,
+}
+
+impl ToolUpgrade {
+    pub fn price(self) -> u32 {
+        match self {
+            ToolUpgrade::ThinBlade => 30,
+            ToolUpgrade::BevelGauge => 25,
+        }
+    }
+}
+
+/// Where delivered planks are stacked.
+const DELIVERY_POSITION: Vec2 = Vec2::new(-350., 5.);
+/// Gap between delivered planks in the stack.
+const DELIVERY_GAP: f32 = 1.;
+
+impl Stock {
+    const NAME: &'static str = "stock";
+
+    pub fn store(&self) -> Result<(), SaveError> {
+        write_save(Self::NAME, &ron::ser::to_string_pretty(self, ron::ser::PrettyConfig::default())?)
+    }
+
+    pub fn load() -> Result<Self, SaveError> {
+        match read_save(Self::NAME)? {
+            Some(contents) => Ok(ron::de::from_str(&contents)?),
+            None => Ok(default()),
+        }
+    }
+
+    pub fn has(&self, upgrade: ToolUpgrade) -> bool {
+        self.upgrades.contains(&upgrade)
+    }
+
+    /// Width of the wood lost to a cut with the current blade.
+    pub fn kerf(&self) -> f32 {
+        if self.has(ToolUpgrade::ThinBlade) {
+            CUT_SIZE / 2.
+        } else {
+            CUT_SIZE
+        }
+    }
+
+    /// Takes a fastener of `kind` out of stock, if there is one.
+    pub fn take_fastener(&mut self, kind: FastenerKind) -> bool {
+        match self.fasteners.get_mut(&kind) {
+            Some(count) if *count > 0 => {
+                *count -= 1;
+                true
+            }
+            _ => false,
+        }
+    }
+
+    /// Takes out all the planks waiting for delivery, stacked where levels deliver them.
+    pub fn deliver_planks(&mut self, asset_server: &AssetServer) -> Vec<PlankDef> {
+        let mut y = DELIVERY_POSITION.y;
+        std::mem::take(&mut self.planks)
+            .into_iter()
+            .map(|plank| {
+                let position = Vec2::new(DELIVERY_POSITION.x, y + plank.size.y / 2.);
+                y += plank.size.y + DELIVERY_GAP;
+                PlankDef {
+                    position,
+                    size: plank.size,
+                    rotation: 0.,
+                    color: None,
+                    species_handle: asset_server.load(plank.species.clone()),
+                    species: Some(plank.species),
+                    grain: 0.,
+                }
+            })
+            .collect()
+    }
+
+    /// Pays `price` for an offer and adds what it sells to the stock. Returns whether it could be
+    /// bought.
+    pub fn buy(&mut self, offer: &Offer, price: u32) -> bool {
+        if self.money < price {
+            return false;
+        }
+        match offer.item {
+            ShopItem::Plank { size, species } => self.planks.push(StockPlank {
+                size,
+                species: species.to_string(),
+            }),
+            ShopItem::Fasteners { kind, count } => *self.fasteners.entry(kind).or_default() += count,
+            ShopItem::Upgrade(upgrade) if self.has(upgrade) => return false,
+            ShopItem::Upgrade(upgrade) => self.upgrades.push(upgrade),
+        }
+        self.money -= price;
+        true
+    }
+}
+
+pub enum ShopItem {
+    Plank {
+        size: Vec2,
+        /// Path of the [`WoodSpecies`], relative to the assets folder.
+        species: &'static str,
+    },
+    Fasteners {
+        kind: FastenerKind,
+        count: u32,
+    },
+    Upgrade(ToolUpgrade),
+}
+
+/// Something for sale at the lumber yard.
+pub struct Offer {
+    pub name: &'static str,
+    pub item: ShopItem,
+}
+
+impl Offer {
+    /// Planks are priced by their species and length, fasteners by what they cost to use.
+    /// `None` if the species isn't loaded.
+    pub fn price(&self, species: &Assets<WoodSpecies>, asset_server: &AssetServer) -> Option<u32> {
+        match self.item {
+            ShopItem::Plank { size, species: path } => asset_server
+                .get_handle(path)
+                .and_then(|handle| species.get(&handle))
+                .map(|s| s.plank_price(size.x)),
+            ShopItem::Fasteners { kind, count } => Some(kind.spec().cost * count),
+            ShopItem::Upgrade(upgrade) => Some(upgrade.price()),
+        }
+    }
+}
+
+/// Everything the lumber yard sells.
+pub const CATALOGUE: [Offer; 7] = [
+    Offer {
+        name: "Pine plank, 120 x 10",
+        item: ShopItem::Plank {
+            size: Vec2::new(120., 10.),
+            species: "wood/pine.species.ron",
+        },
+    },
+    Offer {
+        name: "Oak plank, 160 x 10",
+        item: ShopItem::Plank {
+            size: Vec2::new(160., 10.),
+            species: "wood/oak.species.ron",
+        },
+    },
+    Offer {
+        name: "Walnut plank, 100 x 12",
+        item: ShopItem::Plank {
+            size: Vec2::new(100., 12.),
+            species: "wood/walnut.species.ron",
+        },
+    },
+    Offer {
+        name: "Box of 10 screws",
+        item: ShopItem::Fasteners {
+            kind: FastenerKind::Screw,
+            count: 10,
+        },
+    },
+    Offer {
+        name: "Bag of 4 bolts",
+        item: ShopItem::Fasteners {
+            kind: FastenerKind::Bolt,
+            count: 4,
+        },
+    },
+    Offer {
+        name: "Thin-kerf blade",
+        item: ShopItem::Upgrade(ToolUpgrade::ThinBlade),
+    },
+    Offer {
+        name: "Bevel gauge",
+        item: ShopItem::Upgrade(ToolUpgrade::BevelGauge),
+    },
+];
+
+fn store_stock(stock: Res<Stock>) {
+    if let Err(e) = stock.store() {
+        error!("{e}");
+    }
+}
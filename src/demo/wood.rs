@@ -0,0 +1,205 @@
+//! Wood species, which decide how heavy, grippy and bouncy planks are and how well they hold
+//! fasteners.
+//!
+//! Species are described by `.species.ron` files in `assets/wood`, loaded as [`WoodSpecies`]
+//! assets. Every plank carries its species in a [`PlankSpecies`], and the direction of its
+//! grain in a [`Grain`]. Sawing across the grain is slower and tears out more wood than sawing
+//! along it, and fasteners driven into end grain hold less.
+
+use crate::asset_tracking::LoadResource;
+use bevy::asset::io::Reader;
+use bevy::asset::{AssetLoader, AsyncReadExt, LoadContext};
+use bevy::prelude::*;
+use bevy::render::texture::{
+    ImageAddressMode, ImageLoaderSettings, ImageSampler, ImageSamplerDescriptor,
+};
+use rand::Rng;
+use serde::{Deserialize, Serialize};
+
+pub(super) fn plugin(app: &mut App) {
+    app.init_asset::<WoodSpecies>();
+    app.register_asset_loader(WoodSpeciesLoader);
+    app.load_resource::<WoodAssets>();
+}
+
+#[derive(Asset, TypePath, Serialize, Deserialize, Clone, Debug)]
+pub struct WoodSpecies {
+    pub name: String,
+    /// Mass per unit of area.
+    pub density: f32,
+    pub friction: f32,
+    pub restitution: f32,
+    /// Scales how much load joints in this wood take before they break.
+    pub joint_strength: f32,
+    /// sRGB colours the wood's colour is picked between.
+    pub colors: [[f32; 3]; 2],
+    /// Path of the grain texture, relative to the assets folder.
+    pub grain: String,
+    #[serde(skip)]
+    pub grain_handle: Handle<Image>,
+    /// Price of a plank 100 long at the lumber yard.
+    pub price: u32,
+}
+
+impl Default for WoodSpecies {
+    /// Plain wood, used while a plank's species isn't loaded.
+    fn default() -> Self {
+        Self {
+            name: "Wood".to_string(),
+            density: 1.,
+            friction: 0.5,
+            restitution: 0.,
+            joint_strength: 1.,
+            colors: [[0.25, 0.12, 0.01], [0.35, 0.18, 0.01]],
+            grain: String::new(),
+            grain_handle: default(),
+            price: 5,
+        }
+    }
+}
+
+impl WoodSpecies {
+    /// A colour from the species' range, so that planks of the same wood don't all look alike.
+    pub fn random_color(&self, rng: &mut impl Rng) -> Color {
+        let [a, b] = self.colors.map(Vec3::from);
+        let [r, g, b] = a.lerp(b, rng.gen_range(0f32..1.)).to_array();
+        Color::srgb(r, g, b)
+    }
+
+    /// Price of a plank of the given length.
+    pub fn plank_price(&self, length: f32) -> u32 {
+        (self.price as f32 * length / 100.).ceil() as u32
+    }
+}
+
+/// The species of a plank.
+#[derive(Component, Clone)]
+pub struct PlankSpecies(pub Handle<WoodSpecies>);
+
+/// Feed rate of the saw through wood when cutting along the grain, in units per second.
+const RIP_FEED: f32 = 12.;
+/// Feed rate of the saw through wood when cutting straight across the grain.
+const CROSSCUT_FEED: f32 = 4.;
+/// Extra kerf, as a fraction of the blade's, torn out by a cut straight across the grain.
+const TEAR_OUT: f32 = 1.;
+/// Fraction of its strength a fastener keeps when driven straight into end grain.
+const END_GRAIN_HOLD: f32 = 0.3;
+
+/// The direction the grain of a plank runs in, as an angle in radians from the plank's local x
+/// axis. It turns with the plank and carries over to the pieces it is cut into.
+#[derive(Component, Clone, Copy, Default, Debug, PartialEq)]
+pub struct Grain(pub f32);
+
+impl Grain {
+    /// Direction of the grain in the plank's local frame.
+    pub fn direction(self) -> Vec2 {
+        Vec2::from_angle(self.0)
+    }
+
+    /// How squarely a line with the given direction, in the plank's local frame, crosses the
+    /// grain: 0 along it, 1 straight across.
+    pub fn across(self, direction: Vec2) -> f32 {
+        self.direction().perp_dot(direction.normalize_or_zero()).abs()
+    }
+
+    /// Like [`Grain::across`], averaged over the segments of a path.
+    pub fn across_path(self, path: &[Vec2]) -> f32 {
+        let (across, length) = path.windows(2).fold((0., 0.), |(across, length), w| {
+            let segment = w[1] - w[0];
+            (across + self.across(segment) * segment.length(), length + segment.length())
+        });
+        if length > 0. {
+            across / length
+        } else {
+            0.
+        }
+    }
+
+    /// How fast a saw can cut through the plank along a line with the given direction.
+    pub fn saw_feed(self, cut: Vec2) -> f32 {
+        RIP_FEED + (CROSSCUT_FEED - RIP_FEED) * self.across(cut)
+    }
+
+    /// Width of wood lost on each side of a cut by a blade with the given kerf, given how
+    /// squarely the cut crosses the grain. Cuts across the grain splinter and leave a rougher
+    /// edge.
+    pub fn kerf(kerf: f32, across: f32) -> f32 {
+        kerf * (1. + TEAR_OUT * across)
+    }
+
+    /// Fraction of its strength a fastener keeps when driven in the given direction: the closer
+    /// it runs to the grain, the more it goes into end grain and the less it holds.
+    pub fn fastener_hold(self, direction: Vec2) -> f32 {
+        let along = 1. - self.across(direction);
+        1. + (END_GRAIN_HOLD - 1.) * along
+    }
+}
+
+#[derive(Default)]
+pub struct WoodSpeciesLoader;
+
+#[derive(Debug, thiserror::Error)]
+pub enum WoodSpeciesLoaderError {
+    #[error("could not read wood species file: {0}")]
+    Io(#[from] std::io::Error),
+    #[error("could not parse wood species file: {0}")]
+    Ron(#[from] ron::error::SpannedError),
+}
+
+impl AssetLoader for WoodSpeciesLoader {
+    type Asset = WoodSpecies;
+    type Settings = ();
+    type Error = WoodSpeciesLoaderError;
+
+    async fn load<'a>(
+        &'a self,
+        reader: &'a mut Reader<'_>,
+        _settings: &'a (),
+        load_context: &'a mut LoadContext<'_>,
+    ) -> Result<WoodSpecies, WoodSpeciesLoaderError> {
+        let mut bytes = Vec::new();
+        reader.read_to_end(&mut bytes).await?;
+        let mut species: WoodSpecies = ron::de::from_bytes(&bytes)?;
+        // Grain textures tile along planks of any size.
+        species.grain_handle = load_context
+            .loader()
+            .with_settings(|settings: &mut ImageLoaderSettings| {
+                settings.sampler = ImageSampler::Descriptor(ImageSamplerDescriptor {
+                    address_mode_u: ImageAddressMode::Repeat,
+                    address_mode_v: ImageAddressMode::Repeat,
+                    ..ImageSamplerDescriptor::linear()
+                });
+            })
+            .load(species.grain.clone());
+        Ok(species)
+    }
+
+    fn extensions(&self) -> &[&str] {
+        &["species.ron"]
+    }
+}
+
+#[derive(Resource, Asset, Reflect, Clone)]
+pub struct WoodAssets {
+    #[dependency]
+    pub species: Vec<Handle<WoodSpecies>>,
+}
+
+impl WoodAssets {
+    /// The wood planks are made of unless a level says otherwise.
+    pub const PATH_DEFAULT: &'static str = "wood/pine.species.ron";
+    pub const PATHS: [&'static str; 3] = [
+        Self::PATH_DEFAULT,
+        "wood/oak.species.ron",
+        "wood/walnut.species.ron",
+    ];
+}
+
+impl FromWorld for WoodAssets {
+    fn from_world(world: &mut World) -> Self {
+        let assets = world.resource::<AssetServer>();
+        Self {
+            species: Self::PATHS.iter().map(|path| assets.load(*path)).collect(),
+        }
+    }
+}
@@ -25,6 +25,7 @@ fn spawn_credits_screen(mut commands: Commands) {
             children.label("Bevy logo - All rights reserved by the Bevy Foundation. Permission granted for splash screen use when unmodified.");
             children.label("Ducky sprite - CC0 by Caz Creates Games");
             children.label("Button SFX - CC0 by Jaszunio15");
+            children.label("Snap SFX - CC0, made for this game");
             children.label("Music - CC BY 3.0 by Kevin MacLeod");
 
             children.button("Back").observe(enter_title_screen);
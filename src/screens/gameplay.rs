@@ -3,13 +3,12 @@
 use bevy::{input::common_conditions::input_just_pressed, prelude::*};
 
 use crate::{
-    asset_tracking::LoadResource, audio::Music, demo::level::spawn_level as spawn_level_command, demo::level::spawn_banana,
+    asset_tracking::LoadResource, audio::Music, demo::level::spawn_level as spawn_level_command, demo::save::restore_pending,
     screens::Screen,
 };
 
 pub(super) fn plugin(app: &mut App) {
-    app.add_systems(OnEnter(Screen::Gameplay), spawn_level);
-    app.add_systems(OnEnter(Screen::Gameplay), spawn_banana);
+    app.add_systems(OnEnter(Screen::Gameplay), (spawn_level, restore_pending).chain());
 
     app.load_resource::<GameplayMusic>();
     // app.add_systems(OnEnter(Screen::Gameplay), play_gameplay_music);
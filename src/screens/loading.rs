@@ -4,6 +4,7 @@
 use bevy::prelude::*;
 
 use crate::{
+    demo::{campaign::CampaignAssets, joint::JointAssets, level::LevelAssets, wood::WoodAssets},
     screens::{credits::CreditsMusic, gameplay::GameplayMusic, Screen},
     theme::{interaction::InteractionAssets, prelude::*},
 };
@@ -37,6 +38,16 @@ fn all_assets_loaded(
     interaction_assets: Option<Res<InteractionAssets>>,
     credits_music: Option<Res<CreditsMusic>>,
     gameplay_music: Option<Res<GameplayMusic>>,
+    joint_assets: Option<Res<JointAssets>>,
+    level_assets: Option<Res<LevelAssets>>,
+    campaign_assets: Option<Res<CampaignAssets>>,
+    wood_assets: Option<Res<WoodAssets>>,
 ) -> bool {
-    interaction_assets.is_some() && credits_music.is_some() && gameplay_music.is_some()
+    interaction_assets.is_some()
+        && credits_music.is_some()
+        && gameplay_music.is_some()
+        && joint_assets.is_some()
+        && level_assets.is_some()
+        && campaign_assets.is_some()
+        && wood_assets.is_some()
 }
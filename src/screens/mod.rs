@@ -1,8 +1,12 @@
 //! The game's main screen states and transitions between them.
 
 mod credits;
+mod editor;
 mod gameplay;
 mod loading;
+mod orders;
+mod results;
+mod shop;
 mod splash;
 mod title;
 
@@ -14,8 +18,12 @@ pub(super) fn plugin(app: &mut App) {
 
     app.add_plugins((
         credits::plugin,
+        editor::plugin,
         gameplay::plugin,
         loading::plugin,
+        orders::plugin,
+        results::plugin,
+        shop::plugin,
         splash::plugin,
         title::plugin,
     ));
@@ -30,4 +38,11 @@ pub enum Screen {
     Title,
     Credits,
     Gameplay,
+    Editor,
+    /// Picking a campaign order.
+    Orders,
+    /// The lumber yard, between orders.
+    Shop,
+    /// How the submitted build did.
+    Results,
 }
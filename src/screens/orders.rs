@@ -0,0 +1,74 @@
+//! The order select screen, where campaign orders are picked.
+
+use bevy::prelude::*;
+
+use crate::{
+    demo::{
+        campaign::{ActiveOrder, Campaign, CampaignAssets, CampaignProgress},
+        level::{CurrentLevel, LevelDef},
+    },
+    screens::Screen,
+    theme::prelude::*,
+};
+
+pub(super) fn plugin(app: &mut App) {
+    app.add_systems(OnEnter(Screen::Orders), spawn_orders_screen);
+}
+
+fn spawn_orders_screen(
+    mut commands: Commands,
+    campaign_assets: Res<CampaignAssets>,
+    campaigns: Res<Assets<Campaign>>,
+    levels: Res<Assets<LevelDef>>,
+    progress: Res<CampaignProgress>,
+) {
+    let Some(campaign) = campaigns.get(&campaign_assets.campaign) else {
+        error!("The campaign is not loaded");
+        return;
+    };
+    commands
+        .ui_root()
+        .insert(StateScoped(Screen::Orders))
+        .with_children(|children| {
+            children.header("Orders");
+            for (index, (path, handle)) in campaign.orders.iter().zip(&campaign.levels).enumerate() {
+                let Some(order) = levels.get(handle).and_then(|l| l.order.as_ref()) else {
+                    warn!("Campaign level {path} has no order");
+                    continue;
+                };
+                if !progress.is_unlocked(campaign, index) {
+                    children.label(format!("{}: locked", order.customer));
+                    continue;
+                }
+                let stars = progress
+                    .fulfilled
+                    .get(path)
+                    .map_or(String::new(), |stars| format!(", done with {stars} of 3 stars"));
+                children.label(format!(
+                    "\"{}\" (pays {}, budget {}{stars})",
+                    order.request, order.payment, order.budget
+                ));
+                let active = ActiveOrder { path: path.clone() };
+                let handle = handle.clone();
+                children.button(order.customer.clone()).observe(
+                    move |_trigger: Trigger<OnPress>,
+                          mut next_screen: ResMut<NextState<Screen>>,
+                          mut commands: Commands| {
+                        commands.insert_resource(active.clone());
+                        commands.insert_resource(CurrentLevel(handle.clone()));
+                        next_screen.set(Screen::Gameplay);
+                    },
+                );
+            }
+            children.button("Lumber yard").observe(enter_shop_screen);
+            children.button("Back").observe(enter_title_screen);
+        });
+}
+
+fn enter_shop_screen(_trigger: Trigger<OnPress>, mut next_screen: ResMut<NextState<Screen>>) {
+    next_screen.set(Screen::Shop);
+}
+
+fn enter_title_screen(_trigger: Trigger<OnPress>, mut next_screen: ResMut<NextState<Screen>>) {
+    next_screen.set(Screen::Title);
+}
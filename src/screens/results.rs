@@ -0,0 +1,90 @@
+//! The screen shown after a build is submitted, with its score.
+
+use bevy::prelude::*;
+
+use crate::{
+    demo::{campaign::ActiveOrder, load_test::LoadTestOutcome, score::BuildResult},
+    screens::Screen,
+    theme::prelude::*,
+};
+
+pub(super) fn plugin(app: &mut App) {
+    app.add_systems(OnEnter(Screen::Results), spawn_results_screen);
+}
+
+fn spawn_results_screen(
+    mut commands: Commands,
+    result: Res<BuildResult>,
+    active_order: Option<Res<ActiveOrder>>,
+) {
+    let campaign = active_order.is_some();
+    let stars = result.stars();
+    let test = match result.test {
+        Some(LoadTestOutcome::Passed) => "passed",
+        Some(LoadTestOutcome::Failed) => "failed",
+        None => "not tested",
+    };
+    let precision = result
+        .cut_error
+        .map_or("-".to_string(), |e| format!("{e:.1} off on average"));
+    let seconds = result.time.as_secs();
+
+    commands
+        .ui_root()
+        .insert(StateScoped(Screen::Results))
+        .with_children(|children| {
+            children.header(result.blueprint.as_deref().unwrap_or("Results"));
+            children.label(format!("{stars} of 3 stars"));
+            children.label(format!("Blueprint: {:.0}% complete", result.completion * 100.));
+            children.label(format!(
+                "Wood: {:.0} used, {:.0} wasted",
+                result.material_used, result.material_wasted
+            ));
+            children.label(format!("Cuts: {}, precision: {precision}", result.cuts));
+            children.label(format!("Fasteners: {}", result.fasteners));
+            children.label(format!("Load test: {test}"));
+            children.label(format!("Time: {}:{:02}", seconds / 60, seconds % 60));
+            if let Some(order) = &result.order {
+                children.label(format!("Spent {} of a {} budget", result.spent, order.budget));
+                if let Some(min_height) = order.min_height {
+                    children.label(format!("Height: {:.0} of at least {min_height:.0}", result.height));
+                }
+                children.label(if result.fulfilled == Some(true) {
+                    format!("{} is happy with it!", order.customer)
+                } else {
+                    format!("{} won't take it.", order.customer)
+                });
+            }
+
+            children.button("Export cut list").observe(export_cut_list);
+            children.button("Play again").observe(enter_gameplay_screen);
+            if campaign {
+                children.button("Lumber yard").observe(enter_shop_screen);
+                children.button("Orders").observe(enter_orders_screen);
+            }
+            children.button("Title").observe(enter_title_screen);
+        });
+}
+
+fn export_cut_list(_trigger: Trigger<OnPress>, result: Res<BuildResult>) {
+    match result.cut_list.export() {
+        Ok(paths) => info!("Exported the cut list to {}", paths.join(", ")),
+        Err(e) => error!("Could not export the cut list: {e}"),
+    }
+}
+
+fn enter_gameplay_screen(_trigger: Trigger<OnPress>, mut next_screen: ResMut<NextState<Screen>>) {
+    next_screen.set(Screen::Gameplay);
+}
+
+fn enter_shop_screen(_trigger: Trigger<OnPress>, mut next_screen: ResMut<NextState<Screen>>) {
+    next_screen.set(Screen::Shop);
+}
+
+fn enter_orders_screen(_trigger: Trigger<OnPress>, mut next_screen: ResMut<NextState<Screen>>) {
+    next_screen.set(Screen::Orders);
+}
+
+fn enter_title_screen(_trigger: Trigger<OnPress>, mut next_screen: ResMut<NextState<Screen>>) {
+    next_screen.set(Screen::Title);
+}
@@ -0,0 +1,77 @@
+//! The lumber yard screen, where stock is bought between orders.
+
+use bevy::prelude::*;
+
+use crate::{
+    demo::{
+        shop::{Stock, CATALOGUE},
+        wood::WoodSpecies,
+    },
+    screens::Screen,
+    theme::prelude::*,
+};
+
+pub(super) fn plugin(app: &mut App) {
+    app.add_systems(OnEnter(Screen::Shop), spawn_shop_screen);
+    app.add_systems(
+        Update,
+        update_stock_label.run_if(in_state(Screen::Shop).and_then(resource_changed::<Stock>)),
+    );
+}
+
+/// Text showing the player's money and what is waiting to be delivered.
+#[derive(Component)]
+struct StockLabel;
+
+fn spawn_shop_screen(
+    mut commands: Commands,
+    stock: Res<Stock>,
+    species: Res<Assets<WoodSpecies>>,
+    asset_server: Res<AssetServer>,
+) {
+    commands
+        .ui_root()
+        .insert(StateScoped(Screen::Shop))
+        .with_children(|children| {
+            children.label(stock_text(&stock)).insert(StockLabel);
+            children.list().with_children(|list| {
+                for (index, offer) in CATALOGUE.iter().enumerate() {
+                    let Some(price) = offer.price(&species, &asset_server) else {
+                        continue;
+                    };
+                    list.list_item(offer.name).with_children(|item| {
+                        item.price(price);
+                        item.button("Buy").observe(
+                            move |_trigger: Trigger<OnPress>, mut stock: ResMut<Stock>| {
+                                let offer = &CATALOGUE[index];
+                                if !stock.buy(offer, price) {
+                                    info!("Can't buy {}", offer.name);
+                                }
+                            },
+                        );
+                    });
+                }
+            });
+            children.button("Orders").observe(enter_orders_screen);
+        });
+}
+
+fn stock_text(stock: &Stock) -> String {
+    let fasteners: u32 = stock.fasteners.values().sum();
+    format!(
+        "${} to spend. In stock: {} planks, {fasteners} fasteners",
+        stock.money,
+        stock.planks.len()
+    )
+}
+
+fn update_stock_label(stock: Res<Stock>, mut labels: Query<&mut Text, With<StockLabel>>) {
+    let text = stock_text(&stock);
+    for mut label in &mut labels {
+        label.sections[0].value.clone_from(&text);
+    }
+}
+
+fn enter_orders_screen(_trigger: Trigger<OnPress>, mut next_screen: ResMut<NextState<Screen>>) {
+    next_screen.set(Screen::Orders);
+}
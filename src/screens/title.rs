@@ -2,18 +2,34 @@
 
 use bevy::prelude::*;
 
-use crate::{screens::Screen, theme::prelude::*};
+use crate::{
+    demo::{
+        campaign::ActiveOrder,
+        level::{CurrentLevel, LevelAssets},
+        save::{PendingRestore, WorkshopSave},
+        seed::{random_seed, seed_text, LevelSeed, SeedLabel},
+    },
+    screens::Screen,
+    theme::prelude::*,
+};
 
 pub(super) fn plugin(app: &mut App) {
     app.add_systems(OnEnter(Screen::Title), spawn_title_screen);
 }
 
-fn spawn_title_screen(mut commands: Commands) {
+fn spawn_title_screen(mut commands: Commands, seed: Res<LevelSeed>) {
     commands
         .ui_root()
         .insert(StateScoped(Screen::Title))
         .with_children(|children| {
+            if matches!(WorkshopSave::load(), Ok(Some(_))) {
+                children.button("Continue").observe(continue_saved_workshop);
+            }
             children.button("Play").observe(enter_gameplay_screen);
+            children.button("Campaign").observe(enter_orders_screen);
+            children.label(seed_text(*seed)).insert(SeedLabel);
+            children.button("New seed").observe(reroll_seed);
+            children.button("Editor").observe(enter_editor_screen);
             children.button("Credits").observe(enter_credits_screen);
 
             #[cfg(not(target_family = "wasm"))]
@@ -21,10 +37,49 @@ fn spawn_title_screen(mut commands: Commands) {
         });
 }
 
-fn enter_gameplay_screen(_trigger: Trigger<OnPress>, mut next_screen: ResMut<NextState<Screen>>) {
+/// Plays the workshop, outside of the campaign.
+fn enter_gameplay_screen(
+    _trigger: Trigger<OnPress>,
+    mut next_screen: ResMut<NextState<Screen>>,
+    level_assets: Res<LevelAssets>,
+    mut commands: Commands,
+) {
+    commands.remove_resource::<ActiveOrder>();
+    commands.insert_resource(CurrentLevel(level_assets.workshop.clone()));
     next_screen.set(Screen::Gameplay);
 }
 
+fn enter_orders_screen(_trigger: Trigger<OnPress>, mut next_screen: ResMut<NextState<Screen>>) {
+    next_screen.set(Screen::Orders);
+}
+
+/// Continues the saved workshop in the level it was saved in.
+fn continue_saved_workshop(
+    _trigger: Trigger<OnPress>,
+    mut next_screen: ResMut<NextState<Screen>>,
+    mut commands: Commands,
+) {
+    match WorkshopSave::load() {
+        Ok(Some(save)) => commands.add(move |world: &mut World| {
+            if let Some(level) = &save.level {
+                level.restore(world);
+            }
+            world.insert_resource(PendingRestore(save));
+        }),
+        Ok(None) => {}
+        Err(e) => error!("{e}"),
+    }
+    next_screen.set(Screen::Gameplay);
+}
+
+fn reroll_seed(_trigger: Trigger<OnPress>, mut seed: ResMut<LevelSeed>) {
+    seed.0 = random_seed();
+}
+
+fn enter_editor_screen(_trigger: Trigger<OnPress>, mut next_screen: ResMut<NextState<Screen>>) {
+    next_screen.set(Screen::Editor);
+}
+
 fn enter_credits_screen(_trigger: Trigger<OnPress>, mut next_screen: ResMut<NextState<Screen>>) {
     next_screen.set(Screen::Credits);
 }
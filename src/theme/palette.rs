@@ -6,5 +6,6 @@ pub const BUTTON_PRESSED_BACKGROUND: Color = Color::srgb(0.286, 0.478, 0.773);
 pub const BUTTON_TEXT: Color = Color::srgb(0.925, 0.925, 0.925);
 pub const LABEL_TEXT: Color = Color::srgb(0.867, 0.827, 0.412);
 pub const HEADER_TEXT: Color = Color::srgb(0.867, 0.827, 0.412);
+pub const PRICE_TEXT: Color = Color::srgb(0.55, 0.85, 0.45);
 
 pub const NODE_BACKGROUND: Color = Color::srgb(0.286, 0.478, 0.773);
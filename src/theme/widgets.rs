@@ -14,6 +14,16 @@ pub trait Widgets {
 
     /// Spawn a simple text label.
     fn label(&mut self, text: impl Into<String>) -> EntityCommands;
+
+    /// Spawn a column for [`Widgets::list_item`]s.
+    fn list(&mut self) -> EntityCommands;
+
+    /// Spawn a row of a [`Widgets::list`], starting with its name. Add prices and buttons to it
+    /// as children.
+    fn list_item(&mut self, name: impl Into<String>) -> EntityCommands;
+
+    /// Spawn a price tag.
+    fn price(&mut self, amount: u32) -> EntityCommands;
 }
 
 impl<T: Spawn> Widgets for T {
@@ -103,6 +113,72 @@ impl<T: Spawn> Widgets for T {
         ));
         entity
     }
+
+    fn list(&mut self) -> EntityCommands {
+        self.spawn((
+            Name::new("List"),
+            NodeBundle {
+                style: Style {
+                    flex_direction: FlexDirection::Column,
+                    row_gap: Px(5.0),
+                    ..default()
+                },
+                ..default()
+            },
+        ))
+    }
+
+    fn list_item(&mut self, name: impl Into<String>) -> EntityCommands {
+        let mut entity = self.spawn((
+            Name::new("List Item"),
+            NodeBundle {
+                style: Style {
+                    flex_direction: FlexDirection::Row,
+                    align_items: AlignItems::Center,
+                    column_gap: Px(10.0),
+                    ..default()
+                },
+                ..default()
+            },
+        ));
+        entity.with_children(|children| {
+            children.spawn((
+                Name::new("List Item Name"),
+                TextBundle::from_section(
+                    name,
+                    TextStyle {
+                        font_size: 24.0,
+                        color: LABEL_TEXT,
+                        ..default()
+                    },
+                )
+                .with_style(Style {
+                    width: Px(300.0),
+                    ..default()
+                }),
+            ));
+        });
+        entity
+    }
+
+    fn price(&mut self, amount: u32) -> EntityCommands {
+        self.spawn((
+            Name::new("Price"),
+            TextBundle::from_section(
+                format!("${amount}"),
+                TextStyle {
+                    font_size: 24.0,
+                    color: PRICE_TEXT,
+                    ..default()
+                },
+            )
+            .with_text_justify(JustifyText::Right)
+            .with_style(Style {
+                width: Px(80.0),
+                ..default()
+            }),
+        ))
+    }
 }
 
 /// An extension trait for spawning UI containers.
@@ -110,6 +186,9 @@ pub trait Containers {
     /// Spawns a root node that covers the full screen
     /// and centers its content horizontally and vertically.
     fn ui_root(&mut self) -> EntityCommands;
+
+    /// Spawns a row along the top of the screen for tool buttons.
+    fn toolbar(&mut self) -> EntityCommands;
 }
 
 impl Containers for Commands<'_, '_> {
@@ -131,6 +210,25 @@ impl Containers for Commands<'_, '_> {
             },
         ))
     }
+
+    fn toolbar(&mut self) -> EntityCommands {
+        self.spawn((
+            Name::new("Toolbar"),
+            NodeBundle {
+                style: Style {
+                    width: Percent(100.0),
+                    justify_content: JustifyContent::Center,
+                    align_items: AlignItems::Center,
+                    flex_direction: FlexDirection::Row,
+                    column_gap: Px(10.0),
+                    top: Px(10.0),
+                    position_type: PositionType::Absolute,
+                    ..default()
+                },
+                ..default()
+            },
+        ))
+    }
 }
 
 /// An internal trait for types that can spawn entities.